@@ -0,0 +1,79 @@
+use crate::word_filter::mask_blocked_words;
+
+const CHAT_MAX_LENGTH: usize = 280;
+// At most CHAT_RATE_LIMIT_MESSAGES messages per player in any CHAT_RATE_WINDOW_MICROS window
+const CHAT_RATE_LIMIT_MESSAGES: u32 = 5;
+const CHAT_RATE_WINDOW_MICROS: i64 = 10 * 1_000_000;
+const CHAT_RETENTION_MICROS: i64 = 24 * 60 * 60 * 1_000_000;
+
+/// A player's chat rate limit window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateWindow {
+    pub started_at_micros: i64,
+    pub messages: u32,
+}
+
+/// Trims a chat message, enforces the length limit and masks blocked words.
+pub fn prepare_message(text: &str) -> Result<String, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Message cannot be empty".to_string());
+    }
+    if text.chars().count() > CHAT_MAX_LENGTH {
+        return Err(format!("Message must be at most {} characters", CHAT_MAX_LENGTH));
+    }
+    Ok(mask_blocked_words(text))
+}
+
+/// Counts one more message against the player's rate limit and returns the updated window.
+///
+/// Fixed window: the count resets once the window that started with the first message has passed.
+pub fn count_message(window: Option<RateWindow>, now_micros: i64) -> Result<RateWindow, String> {
+    match window {
+        Some(window) if now_micros - window.started_at_micros < CHAT_RATE_WINDOW_MICROS => {
+            if window.messages >= CHAT_RATE_LIMIT_MESSAGES {
+                return Err("You are sending messages too quickly".to_string());
+            }
+            Ok(RateWindow { messages: window.messages + 1, ..window })
+        }
+        _ => Ok(RateWindow { started_at_micros: now_micros, messages: 1 }),
+    }
+}
+
+/// Messages sent before this are past the retention window and get purged.
+pub fn retention_cutoff_micros(now_micros: i64) -> i64 {
+    now_micros - CHAT_RETENTION_MICROS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prepare_message_trims_and_masks() {
+        assert_eq!(prepare_message("  what the sh1t  ").unwrap(), "what the ****");
+        assert!(prepare_message("   ").unwrap_err().contains("cannot be empty"));
+        assert!(prepare_message(&"a".repeat(CHAT_MAX_LENGTH)).is_ok());
+        assert!(prepare_message(&"a".repeat(CHAT_MAX_LENGTH + 1)).unwrap_err().contains("at most"));
+    }
+
+    #[test]
+    fn test_count_message_limits_each_window() {
+        let mut window = None;
+        for sent in 1..=CHAT_RATE_LIMIT_MESSAGES {
+            let counted = count_message(window, 1_000).unwrap();
+            assert_eq!(counted.messages, sent);
+            window = Some(counted);
+        }
+        assert!(count_message(window, 1_000 + CHAT_RATE_WINDOW_MICROS - 1).unwrap_err().contains("too quickly"));
+
+        let next = count_message(window, 1_000 + CHAT_RATE_WINDOW_MICROS).unwrap();
+        assert_eq!(next, RateWindow { started_at_micros: 1_000 + CHAT_RATE_WINDOW_MICROS, messages: 1 }, "A new window starts");
+    }
+
+    #[test]
+    fn test_retention_cutoff_micros() {
+        let now = 2 * CHAT_RETENTION_MICROS;
+        assert_eq!(retention_cutoff_micros(now), CHAT_RETENTION_MICROS);
+    }
+}
@@ -0,0 +1,226 @@
+pub const CROWD_METER_LIVE: &str = "live"; // Counts published as answers arrive
+pub const CROWD_METER_DELAYED: &str = "delayed"; // Counts published crowd_meter_delay_secs after they happen
+pub const CROWD_METER_AFTER_ANSWER: &str = "after_answer"; // Each player sees the counts as of their own answer
+pub const CROWD_METER_HIDDEN: &str = "hidden"; // Nothing published until the round is scored
+pub const CROWD_METER_FOG: &str = "fog"; // Only percentages are published, never counts
+pub const CROWD_METER_MAX_DELAY_SECS: u32 = 60;
+
+/// The operations `record_answer` needs from the private crowd meter stats, which hold one
+/// row per (round_id, answer_index).
+pub trait CrowdMeterStore {
+    /// Looks up a choice's row through the (round_id, answer_index) index: `(stat_id, count)`.
+    fn find(&self, round_id: u64, answer_index: u32) -> Option<(u64, u32)>;
+    /// Overwrites the count of an existing row.
+    fn update(&mut self, stat_id: u64, round_id: u64, answer_index: u32, count: u32);
+    /// Adds the first row for a choice.
+    fn insert(&mut self, round_id: u64, answer_index: u32, count: u32);
+}
+
+/// Counts one more answer for a choice and returns its new count.
+pub fn record_answer(store: &mut impl CrowdMeterStore, round_id: u64, answer_index: u32) -> u32 {
+    match store.find(round_id, answer_index) {
+        Some((stat_id, count)) => {
+            store.update(stat_id, round_id, answer_index, count + 1);
+            count + 1
+        }
+        None => {
+            store.insert(round_id, answer_index, 1);
+            1
+        }
+    }
+}
+
+/// Count per answer index from `(answer_index, count)` rows; choices without a row count zero.
+pub fn counts_by_index(stats: impl IntoIterator<Item = (u32, u32)>) -> Vec<u32> {
+    let mut counts = Vec::new();
+    for (answer_index, count) in stats {
+        let index = answer_index as usize;
+        if counts.len() <= index {
+            counts.resize(index + 1, 0);
+        }
+        counts[index] = count;
+    }
+    counts
+}
+
+/// Rounded share of `count` in `total`, as a whole percentage.
+pub fn percent_of(count: u32, total: u32) -> u8 {
+    if total == 0 {
+        return 0;
+    }
+    ((count as f32 * 100.0 / total as f32).round()) as u8
+}
+
+/// Canonical mode and delay for a host's crowd meter setting. Only the delayed mode takes a
+/// delay; every other mode stores zero.
+pub fn parse_mode(mode: &str, delay_secs: Option<u32>) -> Result<(&'static str, u32), String> {
+    let modes = [CROWD_METER_LIVE, CROWD_METER_DELAYED, CROWD_METER_AFTER_ANSWER, CROWD_METER_HIDDEN, CROWD_METER_FOG];
+    let requested = mode.trim().to_lowercase();
+    let mode = modes.into_iter().find(|m| *m == requested)
+        .ok_or_else(|| format!("Invalid crowd meter mode: {}. Expected one of: {}", requested, modes.join(", ")))?;
+    if mode != CROWD_METER_DELAYED {
+        return Ok((mode, 0));
+    }
+    let delay_secs = delay_secs.ok_or("Delayed crowd meter mode requires a delay")?;
+    if delay_secs == 0 || delay_secs > CROWD_METER_MAX_DELAY_SECS {
+        return Err(format!("Crowd meter delay must be between 1 and {} seconds", CROWD_METER_MAX_DELAY_SECS));
+    }
+    Ok((mode, delay_secs))
+}
+
+/// What happens to the crowd meter when someone answers.
+#[derive(Debug, PartialEq)]
+pub enum OnAnswer {
+    /// Publish to everyone now, as percentages only in fog mode.
+    Publish { fog: bool },
+    /// Snapshot the counts and publish them this many microseconds later.
+    ReleaseAfterMicros(i64),
+    /// Show the counts only to the player who just answered.
+    SnapshotForAnswerer,
+    /// Publish nothing until the round is scored.
+    Hide,
+}
+
+pub fn on_answer(mode: &str, delay_secs: u32) -> OnAnswer {
+    match mode {
+        CROWD_METER_LIVE => OnAnswer::Publish { fog: false },
+        CROWD_METER_FOG => OnAnswer::Publish { fog: true },
+        CROWD_METER_DELAYED => OnAnswer::ReleaseAfterMicros(delay_secs as i64 * 1_000_000),
+        CROWD_METER_AFTER_ANSWER => OnAnswer::SnapshotForAnswerer,
+        _ => OnAnswer::Hide,
+    }
+}
+
+/// What the public crowd meter shows for one choice.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeterView {
+    pub answer_index: u32,
+    pub count: Option<u32>,
+    pub percent: u8,
+}
+
+/// Public view of every choice; fog hides the counts and keeps the percentages.
+pub fn public_views(counts: &[u32], fog: bool) -> Vec<MeterView> {
+    let total: u32 = counts.iter().sum();
+    counts.iter().enumerate()
+        .map(|(index, &count)| MeterView {
+            answer_index: index as u32,
+            count: if fog { None } else { Some(count) },
+            percent: percent_of(count, total),
+        })
+        .collect()
+}
+
+/// Value to log as a crowd meter change when `view` replaces `previous`: the count, or the
+/// percentage in fog. Nothing is logged for an unchanged view or for an empty choice appearing
+/// for the first time.
+pub fn change_event(previous: Option<&MeterView>, view: &MeterView) -> Option<i64> {
+    if previous == Some(view) {
+        return None;
+    }
+    let value = view.count.map_or(view.percent as i64, |count| count as i64);
+    if previous.is_none() && value == 0 {
+        return None;
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// An in-memory stand-in for the stats table, keyed like its (round_id, answer_index) index.
+    #[derive(Default)]
+    struct MemoryStore {
+        rows: BTreeMap<(u64, u32), (u64, u32)>,
+        next_stat_id: u64,
+    }
+
+    impl CrowdMeterStore for MemoryStore {
+        fn find(&self, round_id: u64, answer_index: u32) -> Option<(u64, u32)> {
+            self.rows.get(&(round_id, answer_index)).copied()
+        }
+
+        fn update(&mut self, stat_id: u64, round_id: u64, answer_index: u32, count: u32) {
+            self.rows.insert((round_id, answer_index), (stat_id, count));
+        }
+
+        fn insert(&mut self, round_id: u64, answer_index: u32, count: u32) {
+            self.next_stat_id += 1;
+            self.rows.insert((round_id, answer_index), (self.next_stat_id, count));
+        }
+    }
+
+    #[test]
+    fn test_record_answer_returns_new_count() {
+        let mut store = MemoryStore::default();
+        assert_eq!(record_answer(&mut store, 1, 2), 1);
+        assert_eq!(record_answer(&mut store, 1, 2), 2);
+        assert_eq!(record_answer(&mut store, 2, 2), 1, "Rounds are counted separately");
+    }
+
+    #[test]
+    fn test_record_answer_keeps_one_row_per_choice() {
+        let mut store = MemoryStore::default();
+        for answer in 0..10u32 {
+            record_answer(&mut store, 1, answer % 4);
+        }
+        let counts: Vec<u32> = store.rows.values().map(|&(_, count)| count).collect();
+        assert_eq!(counts, vec![3, 3, 2, 2], "Answers update their choice's row instead of adding one");
+        let stat_ids: Vec<u64> = store.rows.values().map(|&(stat_id, _)| stat_id).collect();
+        assert_eq!(stat_ids, vec![1, 2, 3, 4], "Rows keep the id they were inserted with");
+    }
+
+    #[test]
+    fn test_counts_by_index_fills_missing_choices() {
+        assert_eq!(counts_by_index([(2, 5), (0, 1)]), vec![1, 0, 5]);
+        assert!(counts_by_index([]).is_empty());
+    }
+
+    #[test]
+    fn test_percent_of() {
+        assert_eq!(percent_of(1, 3), 33);
+        assert_eq!(percent_of(2, 3), 67);
+        assert_eq!(percent_of(0, 0), 0);
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        assert_eq!(parse_mode(" Fog ", Some(5)), Ok((CROWD_METER_FOG, 0)), "Only the delayed mode keeps a delay");
+        assert_eq!(parse_mode("delayed", Some(5)), Ok((CROWD_METER_DELAYED, 5)));
+        assert!(parse_mode("delayed", None).unwrap_err().contains("requires a delay"));
+        assert!(parse_mode("delayed", Some(0)).is_err());
+        assert!(parse_mode("delayed", Some(CROWD_METER_MAX_DELAY_SECS + 1)).is_err());
+        assert!(parse_mode("loud", None).unwrap_err().contains("Invalid crowd meter mode"));
+    }
+
+    #[test]
+    fn test_on_answer_by_mode() {
+        assert_eq!(on_answer(CROWD_METER_LIVE, 0), OnAnswer::Publish { fog: false });
+        assert_eq!(on_answer(CROWD_METER_FOG, 0), OnAnswer::Publish { fog: true });
+        assert_eq!(on_answer(CROWD_METER_DELAYED, 5), OnAnswer::ReleaseAfterMicros(5_000_000));
+        assert_eq!(on_answer(CROWD_METER_AFTER_ANSWER, 0), OnAnswer::SnapshotForAnswerer);
+        assert_eq!(on_answer(CROWD_METER_HIDDEN, 0), OnAnswer::Hide, "Hidden publishes nothing until scoring");
+    }
+
+    #[test]
+    fn test_public_views_fog_publishes_only_percentages() {
+        let views = public_views(&[1, 2], true);
+        assert!(views.iter().all(|v| v.count.is_none()));
+        assert_eq!(views.iter().map(|v| v.percent).collect::<Vec<_>>(), vec![33, 67]);
+        assert_eq!(public_views(&[0, 1], false)[1], MeterView { answer_index: 1, count: Some(1), percent: 100 });
+    }
+
+    #[test]
+    fn test_change_event_skips_unchanged_and_new_empty_choices() {
+        let views = public_views(&[0, 3], false);
+        assert_eq!(change_event(None, &views[0]), None, "An empty choice appearing is not news");
+        assert_eq!(change_event(None, &views[1]), Some(3));
+        assert_eq!(change_event(Some(&views[1]), &views[1]), None);
+
+        let fogged = public_views(&[1, 3], true);
+        assert_eq!(change_event(Some(&views[1]), &fogged[1]), Some(75), "Fog logs the percentage");
+        assert_eq!(change_event(Some(&views[0]), &fogged[0]), Some(25));
+    }
+}
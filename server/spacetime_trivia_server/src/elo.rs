@@ -1,4 +1,29 @@
 const DEFAULT_K_FACTOR: f32 = 24.0;
+pub const INITIAL_ELO: i32 = 1200;
+
+/// Rated games a player must complete before their rating stops being provisional.
+pub const PROVISIONAL_GAME_COUNT: u32 = 10;
+/// Rated games after which a player is considered established.
+const ESTABLISHED_GAME_COUNT: u32 = 30;
+
+const PROVISIONAL_K_FACTOR: f32 = 40.0;
+const DEVELOPING_K_FACTOR: f32 = 32.0;
+const STRONG_K_FACTOR: f32 = 20.0;
+const ELITE_K_FACTOR: f32 = 16.0;
+const STRONG_RATING_THRESHOLD: i32 = 1600;
+const ELITE_RATING_THRESHOLD: i32 = 2000;
+
+pub const DIFFICULTY_EASY: &str = "Easy";
+pub const DIFFICULTY_MEDIUM: &str = "Medium";
+pub const DIFFICULTY_HARD: &str = "Hard";
+
+/// Answers a question needs before its calibrated rating replaces the hand-set difficulty label.
+pub const ITEM_CALIBRATION_ANSWERS: u32 = 20;
+/// K-factor for questions that are past calibration; they see many answers, so each one moves them little.
+const CALIBRATED_ITEM_K_FACTOR: f32 = 8.0;
+const EASY_ITEM_RATING: i32 = 1000;
+const MEDIUM_ITEM_RATING: i32 = 1200;
+const HARD_ITEM_RATING: i32 = 1400;
 
 /// Calculates the expected score for a player based on their rating and the opponent's rating.
 /// Expected score is the probability of the player winning against the opponent.
@@ -24,6 +49,232 @@ pub fn calculate_elo_delta(player_current_elo: i32, opponent_elo: i32, actual_sc
     elo_change.round() as i32
 }
 
+/// Returns true while a player has fewer than `PROVISIONAL_GAME_COUNT` rated games.
+pub fn is_provisional(games_played: u32) -> bool {
+    games_played < PROVISIONAL_GAME_COUNT
+}
+
+/// Chooses the K-factor for a player by experience tier, then by rating band once established.
+///
+/// Provisional players move fastest so they reach their real level quickly, developing players
+/// somewhat less, and established players get a smaller K the higher they are rated.
+pub fn k_factor_for(games_played: u32, elo: i32) -> f32 {
+    if is_provisional(games_played) {
+        PROVISIONAL_K_FACTOR
+    } else if games_played < ESTABLISHED_GAME_COUNT {
+        DEVELOPING_K_FACTOR
+    } else if elo >= ELITE_RATING_THRESHOLD {
+        ELITE_K_FACTOR
+    } else if elo >= STRONG_RATING_THRESHOLD {
+        STRONG_K_FACTOR
+    } else {
+        DEFAULT_K_FACTOR
+    }
+}
+
+/// Starting rating for a question, seeded from its hand-set difficulty label (case-insensitive).
+/// Unknown labels start at the Medium rating.
+pub fn item_rating_for_label(label: &str) -> i32 {
+    if label.eq_ignore_ascii_case(DIFFICULTY_EASY) {
+        EASY_ITEM_RATING
+    } else if label.eq_ignore_ascii_case(DIFFICULTY_HARD) {
+        HARD_ITEM_RATING
+    } else {
+        MEDIUM_ITEM_RATING
+    }
+}
+
+/// Difficulty band shown for a calibrated question rating. Band edges sit halfway between the seed ratings.
+pub fn difficulty_band(item_rating: i32) -> &'static str {
+    if item_rating < (EASY_ITEM_RATING + MEDIUM_ITEM_RATING) / 2 {
+        DIFFICULTY_EASY
+    } else if item_rating < (MEDIUM_ITEM_RATING + HARD_ITEM_RATING) / 2 {
+        DIFFICULTY_MEDIUM
+    } else {
+        DIFFICULTY_HARD
+    }
+}
+
+/// Probability that a player with `player_rating` answers a question rated `item_rating` correctly.
+pub fn expected_success(player_rating: i32, item_rating: i32) -> f32 {
+    calculate_expected_score(player_rating, item_rating)
+}
+
+/// Range of item ratings a player with `player_rating` answers correctly with a probability
+/// between `min_success` and `max_success` (both strictly between 0 and 1).
+///
+/// # Returns
+/// `(lowest_item_rating, highest_item_rating)`, inclusive.
+pub fn item_rating_range_for_success(player_rating: i32, min_success: f32, max_success: f32) -> (i32, i32) {
+    // Inverse of the expected score: item = player + 400 * log10(1/p - 1)
+    let item_rating_at = |success: f32| player_rating as f32 + 400.0 * (1.0 / success - 1.0).log10();
+    (item_rating_at(max_success).ceil() as i32, item_rating_at(min_success).floor() as i32)
+}
+
+/// K-factor for a question's rating: provisional-sized until calibrated, small afterwards.
+pub fn item_k_factor(rated_answers: u32) -> f32 {
+    if rated_answers < ITEM_CALIBRATION_ANSWERS {
+        PROVISIONAL_K_FACTOR
+    } else {
+        CALIBRATED_ITEM_K_FACTOR
+    }
+}
+
+/// Calibrates a question on a round's answers: every answer is a match between its player and
+/// the question, which "wins" when the player gets it wrong. All matches are settled against the
+/// question's pre-round rating and K-factor.
+///
+/// # Arguments
+/// * `results` - `(player_elo, answered_correctly)` for each answer.
+///
+/// # Returns
+/// `(new_item_rating, new_rated_answers)`.
+pub fn calibrate_item(item_rating: i32, rated_answers: u32, results: &[(i32, bool)]) -> (i32, u32) {
+    let k = item_k_factor(rated_answers);
+    let delta: i32 = results.iter()
+        .map(|&(player_elo, correct)| calculate_elo_delta(item_rating, player_elo, if correct { 0.0 } else { 1.0 }, Some(k)))
+        .sum();
+    (item_rating + delta, rated_answers + results.len() as u32)
+}
+
+/// Season soft reset: pulls `elo` toward the starting rating by `factor`
+/// (0.0 keeps it, 1.0 resets it fully).
+pub fn soft_reset_elo(elo: i32, factor: f32) -> i32 {
+    INITIAL_ELO + ((elo - INITIAL_ELO) as f32 * (1.0 - factor)).round() as i32
+}
+
+/// Rating of a team: the mean of its members' ratings, or the starting rating for an empty team.
+pub fn team_rating(member_ratings: &[i32]) -> i32 {
+    if member_ratings.is_empty() {
+        return INITIAL_ELO;
+    }
+    let total: i64 = member_ratings.iter().map(|&r| r as i64).sum();
+    (total as f64 / member_ratings.len() as f64).round() as i32
+}
+
+/// True if players `i` and `j` play against each other: different players who are not teammates.
+/// `team_ids` holds each player's team; players without a team have no teammates.
+pub fn are_opponents<T: PartialEq>(team_ids: &[Option<T>], i: usize, j: usize) -> bool {
+    j != i && (team_ids[i].is_none() || team_ids[j] != team_ids[i])
+}
+
+/// Average pre-game Elo of each player's opponents (see `are_opponents`), 0 for a player without any.
+/// `team_ids` is parallel to `elos`.
+pub fn opponents_average_elo<T: PartialEq>(elos: &[i32], team_ids: &[Option<T>]) -> Vec<i32> {
+    (0..elos.len())
+        .map(|i| {
+            let opponent_elos: Vec<i32> = (0..elos.len()).filter(|&j| are_opponents(team_ids, i, j)).map(|j| elos[j]).collect();
+            opponent_elos.iter().sum::<i32>() / (opponent_elos.len() as i32).max(1)
+        })
+        .collect()
+}
+
+/// Result of one pairwise comparison of final scores: 1.0 for the higher score, 0.5 for a tie, 0.0 otherwise.
+pub fn pairwise_outcome(score: u32, opponent_score: u32) -> f32 {
+    match score.cmp(&opponent_score) {
+        std::cmp::Ordering::Greater => 1.0,
+        std::cmp::Ordering::Equal => 0.5,
+        std::cmp::Ordering::Less => 0.0,
+    }
+}
+
+/// Settles a multiplayer game as a round-robin of pairwise Elo matches.
+///
+/// Every pair of players is compared by final score: the higher score wins, equal scores draw.
+/// The K-factor is divided by the number of opponents so a lobby of any size moves a player's
+/// rating by at most as much as a single head-to-head game would.
+///
+/// Deltas are rounded with the largest-remainder method, so they always sum to zero and
+/// rating is conserved across the lobby.
+///
+/// # Arguments
+/// * `standings` - `(current_elo, final_score)` for each player in the lobby.
+/// * `k_factor` - The K-factor for a full game. Defaults to `DEFAULT_K_FACTOR` if None.
+///
+/// # Returns
+/// The Elo delta for each player, in the same order as `standings`.
+pub fn calculate_multiplayer_elo_deltas(standings: &[(i32, u32)], k_factor: Option<f32>) -> Vec<i32> {
+    let k_factors = vec![k_factor.unwrap_or(DEFAULT_K_FACTOR); standings.len()];
+    calculate_multiplayer_elo_deltas_with_k(standings, &k_factors)
+}
+
+/// Same as `calculate_multiplayer_elo_deltas`, but with a K-factor per player.
+///
+/// Each pairwise match uses the mean of both players' K-factors, which keeps every exchange
+/// zero-sum while still letting provisional players move faster than veterans.
+///
+/// # Arguments
+/// * `standings` - `(current_elo, final_score)` for each player in the lobby.
+/// * `k_factors` - The K-factor for a full game for each player, in the same order as `standings`.
+pub fn calculate_multiplayer_elo_deltas_with_k(standings: &[(i32, u32)], k_factors: &[f32]) -> Vec<i32> {
+    if standings.len() < 2 {
+        return vec![0; standings.len()];
+    }
+    conserving_round(&pairwise_raw_deltas(standings, k_factors))
+}
+
+/// Settles a team game as a round-robin of pairwise Elo matches between teams, each team
+/// playing at its `team_rating`.
+///
+/// Teams may differ in size, so a team's delta is scaled by the mean team size and then shared
+/// among its members: members of a larger team move less, members of a smaller one more, and
+/// the member deltas of the whole lobby still sum to zero after rounding.
+///
+/// # Arguments
+/// * `teams` - `(member_count, team_elo, final_team_score)` for each team. Every team has at least one member.
+/// * `k_factors` - The K-factor for a full game for each team, in the same order as `teams`.
+///
+/// # Returns
+/// The delta of each member, grouped per team in the same order as `teams`.
+pub fn calculate_team_elo_deltas(teams: &[(usize, i32, u32)], k_factors: &[f32]) -> Vec<Vec<i32>> {
+    if teams.len() < 2 {
+        return teams.iter().map(|&(size, _, _)| vec![0; size]).collect();
+    }
+    let standings: Vec<(i32, u32)> = teams.iter().map(|&(_, elo, score)| (elo, score)).collect();
+    let team_deltas = pairwise_raw_deltas(&standings, k_factors);
+    let mean_size = teams.iter().map(|&(size, _, _)| size).sum::<usize>() as f32 / teams.len() as f32;
+
+    let member_raw: Vec<f32> = teams.iter().zip(&team_deltas)
+        .flat_map(|(&(size, _, _), &delta)| std::iter::repeat_n(delta * mean_size / size as f32, size))
+        .collect();
+    let mut member_deltas = conserving_round(&member_raw).into_iter();
+    teams.iter().map(|&(size, _, _)| member_deltas.by_ref().take(size).collect()).collect()
+}
+
+/// Unrounded pairwise deltas for at least two players; they sum to (approximately) zero.
+fn pairwise_raw_deltas(standings: &[(i32, u32)], k_factors: &[f32]) -> Vec<f32> {
+    let opponent_count = (standings.len() - 1) as f32;
+    standings.iter().enumerate()
+        .map(|(i, &(elo, score))| {
+            standings.iter().enumerate()
+                .filter(|(j, _)| *j != i)
+                .map(|(j, &(opponent_elo, opponent_score))| {
+                    let pair_k = (k_factors[i] + k_factors[j]) / 2.0 / opponent_count;
+                    pair_k * (pairwise_outcome(score, opponent_score) - calculate_expected_score(elo, opponent_elo))
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// Rounds deltas that sum to (approximately) zero into integers that sum to exactly zero.
+/// Floors every value, then hands the missing points to the largest fractional remainders.
+fn conserving_round(raw_deltas: &[f32]) -> Vec<i32> {
+    let mut deltas: Vec<i32> = raw_deltas.iter().map(|d| d.floor() as i32).collect();
+    let missing = (-deltas.iter().sum::<i32>()).clamp(0, deltas.len() as i32) as usize;
+
+    let mut by_remainder: Vec<usize> = (0..raw_deltas.len()).collect();
+    by_remainder.sort_by(|&a, &b| {
+        let rem_a = raw_deltas[a] - raw_deltas[a].floor();
+        let rem_b = raw_deltas[b] - raw_deltas[b].floor();
+        rem_b.total_cmp(&rem_a)
+    });
+    for &i in by_remainder.iter().take(missing) {
+        deltas[i] += 1;
+    }
+    deltas
+}
+
 
 #[cfg(test)]
 mod tests {
@@ -100,4 +351,175 @@ mod tests {
         let delta_default_k = calculate_elo_delta(1200, 1200, 1.0, None);
         assert_eq!(delta_default_k, 12); // Should use DEFAULT_K_FACTOR (24.0 * 0.5)
     }
+
+    #[test]
+    fn test_multiplayer_deltas_two_players_match_head_to_head() {
+        // With two players the pairwise settlement is a plain 1v1 game
+        let deltas = calculate_multiplayer_elo_deltas(&[(1200, 30), (1400, 10)], Some(24.0));
+        assert_eq!(deltas, vec![18, -18]);
+    }
+
+    #[test]
+    fn test_multiplayer_deltas_three_players_equal_ratings() {
+        // K is split over 2 opponents: winner +12, middle 0, last -12
+        let deltas = calculate_multiplayer_elo_deltas(&[(1200, 20), (1200, 10), (1200, 5)], Some(24.0));
+        assert_eq!(deltas, vec![12, 0, -12]);
+    }
+
+    #[test]
+    fn test_multiplayer_deltas_ties_are_draws() {
+        // Two players tied on top each draw with each other and beat the third
+        let deltas = calculate_multiplayer_elo_deltas(&[(1200, 20), (1200, 20), (1200, 0)], Some(24.0));
+        assert_eq!(deltas, vec![6, 6, -12]);
+
+        // Everyone tied at equal ratings means nobody moves
+        let all_tied = calculate_multiplayer_elo_deltas(&[(1200, 10), (1200, 10), (1200, 10), (1200, 10)], None);
+        assert_eq!(all_tied, vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_multiplayer_deltas_conserve_total_rating() {
+        let standings = [(1523, 40), (1187, 70), (1302, 10), (998, 40), (1750, 0), (1211, 55)];
+        let deltas = calculate_multiplayer_elo_deltas(&standings, None);
+        assert_eq!(deltas.len(), standings.len());
+        assert_eq!(deltas.iter().sum::<i32>(), 0);
+    }
+
+    #[test]
+    fn test_multiplayer_deltas_with_k_uses_mean_pair_k() {
+        // Provisional (40) vs established (16): the pair plays at K = 28 and stays zero-sum
+        let deltas = calculate_multiplayer_elo_deltas_with_k(&[(1200, 10), (1200, 0)], &[40.0, 16.0]);
+        assert_eq!(deltas, vec![14, -14]);
+    }
+
+    #[test]
+    fn test_multiplayer_deltas_provisional_ties_draw_and_conserve() {
+        // Provisional K = 40, 20 per pair: the tied pair draws each other and both beat the third
+        let k_factors = [k_factor_for(0, 1200); 3];
+        let deltas = calculate_multiplayer_elo_deltas_with_k(&[(1200, 10), (1200, 10), (1200, 0)], &k_factors);
+        assert_eq!(deltas, vec![10, 10, -20]);
+    }
+
+    #[test]
+    fn test_opponents_average_elo_excludes_self_and_teammates() {
+        assert_eq!(opponents_average_elo::<u64>(&[1300, 1200], &[None, None]), vec![1200, 1300]);
+        assert_eq!(opponents_average_elo::<u64>(&[1300, 1200, 1000], &[None, None, None]), vec![1100, 1150, 1250]);
+
+        let teams = [Some(1), Some(1), Some(2), Some(2)];
+        assert_eq!(opponents_average_elo(&[1000, 1200, 1300, 1400], &teams), vec![1350, 1350, 1100, 1100]);
+        assert!(!are_opponents(&teams, 0, 1) && are_opponents(&teams, 0, 2));
+        assert_eq!(opponents_average_elo::<u64>(&[1200], &[None]), vec![0], "Nobody to play against");
+    }
+
+    #[test]
+    fn test_k_factor_for_experience_tiers() {
+        assert_eq!(k_factor_for(0, 1200), PROVISIONAL_K_FACTOR);
+        assert_eq!(k_factor_for(PROVISIONAL_GAME_COUNT - 1, 2500), PROVISIONAL_K_FACTOR);
+        assert_eq!(k_factor_for(PROVISIONAL_GAME_COUNT, 1200), DEVELOPING_K_FACTOR);
+        assert_eq!(k_factor_for(ESTABLISHED_GAME_COUNT - 1, 2500), DEVELOPING_K_FACTOR);
+    }
+
+    #[test]
+    fn test_k_factor_for_established_rating_bands() {
+        assert_eq!(k_factor_for(ESTABLISHED_GAME_COUNT, 1200), DEFAULT_K_FACTOR);
+        assert_eq!(k_factor_for(ESTABLISHED_GAME_COUNT, STRONG_RATING_THRESHOLD), STRONG_K_FACTOR);
+        assert_eq!(k_factor_for(100, ELITE_RATING_THRESHOLD + 150), ELITE_K_FACTOR);
+    }
+
+    #[test]
+    fn test_is_provisional() {
+        assert!(is_provisional(0));
+        assert!(is_provisional(PROVISIONAL_GAME_COUNT - 1));
+        assert!(!is_provisional(PROVISIONAL_GAME_COUNT));
+    }
+
+    #[test]
+    fn test_item_rating_for_label() {
+        assert_eq!(item_rating_for_label("Easy"), EASY_ITEM_RATING);
+        assert_eq!(item_rating_for_label("medium"), MEDIUM_ITEM_RATING);
+        assert_eq!(item_rating_for_label("HARD"), HARD_ITEM_RATING);
+        assert_eq!(item_rating_for_label("Spicy"), MEDIUM_ITEM_RATING); // Unknown labels default to Medium
+    }
+
+    #[test]
+    fn test_difficulty_band_round_trips_seed_ratings() {
+        for label in [DIFFICULTY_EASY, DIFFICULTY_MEDIUM, DIFFICULTY_HARD] {
+            assert_eq!(difficulty_band(item_rating_for_label(label)), label);
+        }
+        assert_eq!(difficulty_band(1099), DIFFICULTY_EASY);
+        assert_eq!(difficulty_band(1100), DIFFICULTY_MEDIUM);
+        assert_eq!(difficulty_band(1300), DIFFICULTY_HARD);
+    }
+
+    #[test]
+    fn test_soft_reset_elo_pulls_toward_initial() {
+        assert_eq!(soft_reset_elo(1400, 0.5), 1300);
+        assert_eq!(soft_reset_elo(1000, 0.5), 1100);
+        assert_eq!(soft_reset_elo(1437, 0.0), 1437);
+        assert_eq!(soft_reset_elo(1437, 1.0), INITIAL_ELO);
+    }
+
+    #[test]
+    fn test_team_rating_is_mean_of_members() {
+        assert_eq!(team_rating(&[1400, 1000]), 1200);
+        assert_eq!(team_rating(&[1300, 1200, 1201]), 1234);
+        assert_eq!(team_rating(&[]), INITIAL_ELO);
+    }
+
+    #[test]
+    fn test_team_deltas_are_zero_sum_for_uneven_teams() {
+        let deltas = calculate_team_elo_deltas(&[(3, 1200, 30), (2, 1200, 10)], &[32.0, 32.0]);
+        assert_eq!(deltas[0].len(), 3);
+        assert_eq!(deltas[1].len(), 2);
+        assert_eq!(deltas.iter().flatten().sum::<i32>(), 0);
+        assert!(deltas[0].iter().all(|&d| d > 0) && deltas[1].iter().all(|&d| d < 0));
+        assert!(deltas[0][0] < -deltas[1][0], "Each member of the larger team moves less");
+    }
+
+    #[test]
+    fn test_team_deltas_match_head_to_head_for_even_teams() {
+        let deltas = calculate_team_elo_deltas(&[(2, 1200, 30), (2, 1200, 10)], &[32.0, 32.0]);
+        assert_eq!(deltas, vec![vec![16, 16], vec![-16, -16]]);
+        assert_eq!(calculate_team_elo_deltas(&[(2, 1200, 30)], &[32.0]), vec![vec![0, 0]]);
+    }
+
+    #[test]
+    fn test_item_k_factor_drops_after_calibration() {
+        assert_eq!(item_k_factor(0), PROVISIONAL_K_FACTOR);
+        assert_eq!(item_k_factor(ITEM_CALIBRATION_ANSWERS), CALIBRATED_ITEM_K_FACTOR);
+    }
+
+    #[test]
+    fn test_calibrate_item_rises_when_missed_and_falls_when_answered() {
+        // Two equally rated players miss a provisional question: it wins both matches at K = 40
+        assert_eq!(calibrate_item(1200, 0, &[(1200, false), (1200, false)]), (1240, 2));
+        assert_eq!(calibrate_item(1200, 0, &[(1200, true)]), (1180, 1));
+        assert_eq!(calibrate_item(1200, 5, &[]), (1200, 5), "A round without answers leaves the question alone");
+    }
+
+    #[test]
+    fn test_calibrate_item_uses_pre_round_k_factor() {
+        // One answer short of calibration: the whole round still moves at the provisional K
+        let (rating, rated_answers) = calibrate_item(1500, ITEM_CALIBRATION_ANSWERS - 1, &[(1200, false), (1200, false)]);
+        assert_eq!(rated_answers, ITEM_CALIBRATION_ANSWERS + 1);
+        assert_eq!(rating, 1500 + 2 * calculate_elo_delta(1500, 1200, 1.0, Some(PROVISIONAL_K_FACTOR)));
+        assert_eq!(difficulty_band(rating), DIFFICULTY_HARD);
+
+        let (calibrated, _) = calibrate_item(1200, ITEM_CALIBRATION_ANSWERS, &[(1200, false)]);
+        assert_eq!(calibrated, 1204, "Calibrated questions move at the small item K");
+    }
+
+    #[test]
+    fn test_item_rating_range_for_success() {
+        let (low, high) = item_rating_range_for_success(1200, 0.4, 0.7);
+        assert!(low < 1200 && high > 1200, "An equally rated question (50%) is inside the band");
+        assert!(expected_success(1200, low) <= 0.7 && expected_success(1200, low - 1) > 0.7);
+        assert!(expected_success(1200, high) >= 0.4 && expected_success(1200, high + 1) < 0.4);
+    }
+
+    #[test]
+    fn test_multiplayer_deltas_fewer_than_two_players() {
+        assert_eq!(calculate_multiplayer_elo_deltas(&[(1200, 10)], None), vec![0]);
+        assert!(calculate_multiplayer_elo_deltas(&[], None).is_empty());
+    }
 }
\ No newline at end of file
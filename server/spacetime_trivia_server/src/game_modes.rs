@@ -0,0 +1,254 @@
+pub const TEAM_SCORING_MAJORITY: &str = "majority"; // The answer most of the team chose counts; the captain breaks ties
+pub const TEAM_SCORING_FIRST_ANSWER: &str = "first_answer"; // The team's fastest answer counts
+pub const TEAM_SCORING_SUM: &str = "sum"; // Every member's points count
+pub const MIN_TEAM_COUNT: u32 = 2;
+pub const MAX_TEAM_COUNT: u32 = 4;
+pub const MAX_ROYALE_LIVES: u32 = 5;
+
+/// Team index (0-based) for each pick of a snake draft over `team_count` teams: picks go to
+/// teams 1, 2, ... n, then n, ... 2, 1, and so on. With players ordered strongest first this
+/// keeps the team ratings close. `team_count` must be at least 1.
+pub fn snake_draft(player_count: usize, team_count: usize) -> Vec<usize> {
+    (0..player_count)
+        .map(|i| {
+            let pick = i % team_count;
+            if (i / team_count).is_multiple_of(2) { pick } else { team_count - 1 - pick }
+        })
+        .collect()
+}
+
+/// The answer most of the team chose, from `(chosen_answer_index, is_captain)` in submission
+/// order. A tie goes to the captain's answer if it is one of the tied choices, otherwise to
+/// whichever tied choice was submitted first.
+pub fn majority_choice(choices: &[(u32, bool)]) -> Option<u32> {
+    let mut counts: std::collections::HashMap<u32, usize> = std::collections::HashMap::new();
+    for &(index, _) in choices {
+        *counts.entry(index).or_default() += 1;
+    }
+    let top = counts.values().copied().max()?;
+    let is_top = |index: u32| counts.get(&index) == Some(&top);
+    choices.iter().find(|&&(index, is_captain)| is_captain && is_top(index))
+        .or_else(|| choices.iter().find(|&&(index, _)| is_top(index)))
+        .map(|&(index, _)| index)
+}
+
+/// Canonical team scoring policy for a host's team settings.
+pub fn parse_team_settings(team_count: u32, team_scoring: &str) -> Result<&'static str, String> {
+    if !(MIN_TEAM_COUNT..=MAX_TEAM_COUNT).contains(&team_count) {
+        return Err(format!("Team count must be between {} and {}", MIN_TEAM_COUNT, MAX_TEAM_COUNT));
+    }
+    let policies = [TEAM_SCORING_MAJORITY, TEAM_SCORING_FIRST_ANSWER, TEAM_SCORING_SUM];
+    let requested = team_scoring.trim().to_lowercase();
+    policies.into_iter().find(|p| *p == requested)
+        .ok_or_else(|| format!("Invalid team scoring: {}. Expected one of: {}", requested, policies.join(", ")))
+}
+
+/// Splits players ordered strongest first into `team_count` snake-drafted teams. Each team's
+/// first member is its strongest and captains it; teams nobody was drafted into stay empty.
+pub fn draft_teams<T>(players: Vec<T>, team_count: usize) -> Vec<Vec<T>> {
+    let mut teams: Vec<Vec<T>> = (0..team_count).map(|_| Vec::new()).collect();
+    let picks = snake_draft(players.len(), team_count);
+    for (player, team_index) in players.into_iter().zip(picks) {
+        teams[team_index].push(player);
+    }
+    teams
+}
+
+/// A team's points for a round under `policy`, from its members' `(chosen_answer_index, points,
+/// is_captain)` in submission order. Sum adds every member's points; the other policies pick
+/// one answer for the team, worth `points_for_correct` when it is right (index 0).
+pub fn team_round_points(policy: &str, answers: &[(u32, u32, bool)], points_for_correct: u32) -> u32 {
+    if policy == TEAM_SCORING_SUM {
+        return answers.iter().map(|&(_, points, _)| points).sum();
+    }
+    let team_choice = if policy == TEAM_SCORING_FIRST_ANSWER {
+        answers.first().map(|&(index, _, _)| index)
+    } else {
+        let choices: Vec<(u32, bool)> = answers.iter().map(|&(index, _, is_captain)| (index, is_captain)).collect();
+        majority_choice(&choices)
+    };
+    if team_choice == Some(0) { points_for_correct } else { 0 }
+}
+
+/// What a battle royale round did to the players still standing, in the order they were given.
+#[derive(Debug, PartialEq)]
+pub struct RoyaleRoundOutcome {
+    pub lives: Vec<u32>,
+    pub placements: Vec<Option<u32>>, // Final placement, None while the player is still in
+    pub over: bool,
+}
+
+/// Settles a battle royale round from `(lives, answered_correctly)` for each player still
+/// standing. A wrong or missing answer costs a life; players left without one are eliminated
+/// and share the placement just below the survivors.
+///
+/// The battle royale is over once at most one player survives or `rounds_played` reaches the
+/// round cap; the survivors then share first place.
+pub fn royale_round_outcome(survivors: &[(u32, bool)], rounds_played: u32, round_cap: Option<u32>) -> RoyaleRoundOutcome {
+    let lives: Vec<u32> = survivors.iter()
+        .map(|&(lives, correct)| if correct { lives } else { lives.saturating_sub(1) })
+        .collect();
+    let alive = lives.iter().filter(|&&l| l > 0).count();
+    let over = alive <= 1 || round_cap.is_some_and(|cap| rounds_played >= cap);
+    let placements = lives.iter()
+        .map(|&l| match (l, over) {
+            (0, _) => Some(alive as u32 + 1),
+            (_, true) => Some(1),
+            (_, false) => None,
+        })
+        .collect();
+    RoyaleRoundOutcome { lives, placements, over }
+}
+
+/// Checks a host's battle royale settings.
+pub fn validate_royale_settings(lives: u32, round_cap: Option<u32>) -> Result<(), String> {
+    if lives == 0 || lives > MAX_ROYALE_LIVES {
+        return Err(format!("Lives must be between 1 and {}", MAX_ROYALE_LIVES));
+    }
+    if round_cap == Some(0) {
+        return Err("Round cap must be at least 1".to_string());
+    }
+    Ok(())
+}
+
+/// Result a battle royale player is settled on: the later out, the better, so first place among
+/// `player_count` players is worth `player_count` and players out in the same round draw.
+/// A player without a placement is still standing and counts as first.
+pub fn royale_result(placement: Option<u32>, player_count: usize) -> u32 {
+    player_count as u32 + 1 - placement.unwrap_or(1)
+}
+
+/// What a buzz did: the points it earned and whether it ends the round.
+#[derive(Debug, PartialEq)]
+pub struct BuzzOutcome {
+    pub points: u32,
+    pub closes_round: bool,
+}
+
+/// Judges a buzz for `chosen_answer_index` (0 is right). A correct buzz takes the round's
+/// points and ends it; a wrong one locks its player out and only ends the round when none of
+/// the `players_left` who have not buzzed yet remain.
+pub fn buzz_outcome(chosen_answer_index: u32, points_for_correct: u32, players_left: usize) -> BuzzOutcome {
+    let points = if chosen_answer_index == 0 { points_for_correct } else { 0 };
+    BuzzOutcome { points, closes_round: points > 0 || players_left == 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snake_draft_alternates_direction() {
+        assert_eq!(snake_draft(4, 2), vec![0, 1, 1, 0]);
+        assert_eq!(snake_draft(6, 3), vec![0, 1, 2, 2, 1, 0]);
+        assert_eq!(snake_draft(5, 2), vec![0, 1, 1, 0, 0], "The odd player out goes to the team that picked last");
+        assert!(snake_draft(0, 2).is_empty());
+    }
+
+    #[test]
+    fn test_majority_choice_takes_most_common_answer() {
+        assert_eq!(majority_choice(&[(2, true), (1, false), (1, false)]), Some(1), "The captain is outvoted");
+        assert_eq!(majority_choice(&[]), None);
+    }
+
+    #[test]
+    fn test_majority_choice_breaks_ties_with_captain_then_submission_order() {
+        assert_eq!(majority_choice(&[(1, false), (0, true)]), Some(0), "The captain breaks a tie");
+        assert_eq!(majority_choice(&[(3, false), (1, false), (2, true), (2, false), (3, false), (1, false)]), Some(2));
+        assert_eq!(majority_choice(&[(3, false), (1, false), (3, false), (1, false), (0, true)]), Some(3), "The captain's choice is not among the tied ones");
+    }
+
+    #[test]
+    fn test_royale_wrong_answers_cost_a_life_and_eliminate_together() {
+        let outcome = royale_round_outcome(&[(1, true), (1, false), (2, false), (1, false)], 1, None);
+        assert_eq!(outcome.lives, vec![1, 0, 1, 0]);
+        assert_eq!(outcome.placements, vec![None, Some(3), None, Some(3)], "Players out in the same round share a placement");
+        assert!(!outcome.over);
+    }
+
+    #[test]
+    fn test_royale_ends_with_last_survivor_or_round_cap() {
+        let last = royale_round_outcome(&[(1, true), (1, false)], 3, None);
+        assert!(last.over);
+        assert_eq!(last.placements, vec![Some(1), Some(2)]);
+
+        let capped = royale_round_outcome(&[(2, false), (1, true), (3, true)], 5, Some(5));
+        assert!(capped.over);
+        assert_eq!(capped.placements, vec![Some(1); 3], "Survivors at the cap share first place");
+
+        let wiped_out = royale_round_outcome(&[(1, false), (1, false)], 2, None);
+        assert!(wiped_out.over);
+        assert_eq!(wiped_out.placements, vec![Some(1), Some(1)]);
+    }
+
+    #[test]
+    fn test_parse_team_settings() {
+        assert_eq!(parse_team_settings(2, " Majority "), Ok(TEAM_SCORING_MAJORITY));
+        assert!(parse_team_settings(MAX_TEAM_COUNT + 1, TEAM_SCORING_SUM).unwrap_err().contains("Team count"));
+        assert!(parse_team_settings(MIN_TEAM_COUNT - 1, TEAM_SCORING_SUM).is_err());
+        assert!(parse_team_settings(2, "loudest").unwrap_err().contains("Invalid team scoring"));
+    }
+
+    #[test]
+    fn test_draft_teams_balances_ratings_with_strongest_as_captain() {
+        let teams = draft_teams(vec![1500, 1400, 1300, 1200], 2);
+        assert_eq!(teams, vec![vec![1500, 1200], vec![1400, 1300]]);
+        assert!(teams.iter().all(|t| crate::elo::team_rating(t) == 1350), "Snake draft balances the team ratings");
+        assert_eq!(draft_teams(vec![1500], 3), vec![vec![1500], vec![], vec![]]);
+    }
+
+    #[test]
+    fn test_team_round_points_by_policy() {
+        // Submission order: a wrong answer first, then the captain's right one
+        let split = [(1, 0, false), (0, 10, true)];
+        assert_eq!(team_round_points(TEAM_SCORING_MAJORITY, &split, 10), 10, "The captain breaks the tie");
+        assert_eq!(team_round_points(TEAM_SCORING_FIRST_ANSWER, &split, 10), 0, "Only the fastest answer counts");
+        assert_eq!(team_round_points(TEAM_SCORING_SUM, &split, 10), 10);
+
+        let fast_right = [(0, 10, false), (1, 0, true), (0, 10, false)];
+        assert_eq!(team_round_points(TEAM_SCORING_FIRST_ANSWER, &fast_right, 20), 20, "A lightning round's points");
+        assert_eq!(team_round_points(TEAM_SCORING_SUM, &fast_right, 10), 20);
+        assert_eq!(team_round_points(TEAM_SCORING_MAJORITY, &[], 10), 0, "A silent team scores nothing");
+    }
+
+    #[test]
+    fn test_validate_royale_settings() {
+        assert!(validate_royale_settings(1, None).is_ok());
+        assert!(validate_royale_settings(MAX_ROYALE_LIVES, Some(1)).is_ok());
+        assert!(validate_royale_settings(0, None).unwrap_err().contains("Lives"));
+        assert!(validate_royale_settings(MAX_ROYALE_LIVES + 1, None).is_err());
+        assert!(validate_royale_settings(1, Some(0)).unwrap_err().contains("Round cap"));
+    }
+
+    #[test]
+    fn test_royale_result_settles_on_elimination_order() {
+        // One survivor and two players out in the same round, all at 1200 and provisional
+        let outcome = royale_round_outcome(&[(1, false), (1, true), (1, false)], 1, None);
+        let results: Vec<u32> = outcome.placements.iter().map(|&p| royale_result(p, 3)).collect();
+        assert_eq!(results, vec![2, 3, 2]);
+        let standings: Vec<(i32, u32)> = results.iter().map(|&r| (1200, r)).collect();
+        let deltas = crate::elo::calculate_multiplayer_elo_deltas_with_k(&standings, &[40.0; 3]);
+        assert_eq!(deltas, vec![-10, 20, -10], "The winner beats both; the eliminated players draw each other");
+        assert_eq!(royale_result(None, 3), 3, "Still standing counts as first");
+    }
+
+    #[test]
+    fn test_royale_shared_first_place_is_a_draw() {
+        let outcome = royale_round_outcome(&[(2, true), (2, false), (2, false)], 1, Some(1));
+        assert_eq!(outcome.lives, vec![2, 1, 1]);
+        let standings: Vec<(i32, u32)> = outcome.placements.iter().map(|&p| (1200, royale_result(p, 3))).collect();
+        assert_eq!(crate::elo::calculate_multiplayer_elo_deltas_with_k(&standings, &[40.0; 3]), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn test_buzz_outcome_wrong_buzz_locks_out_and_correct_buzz_takes_round() {
+        assert_eq!(buzz_outcome(1, 10, 2), BuzzOutcome { points: 0, closes_round: false }, "A wrong buzz leaves the round open");
+        assert_eq!(buzz_outcome(0, 10, 1), BuzzOutcome { points: 10, closes_round: true });
+        assert_eq!(buzz_outcome(0, 20, 0), BuzzOutcome { points: 20, closes_round: true });
+    }
+
+    #[test]
+    fn test_buzz_outcome_round_ends_when_everyone_buzzed_wrong() {
+        assert_eq!(buzz_outcome(2, 10, 0), BuzzOutcome { points: 0, closes_round: true });
+    }
+}
@@ -0,0 +1,240 @@
+pub const DEFAULT_RATING: f64 = 1200.0;
+pub const DEFAULT_DEVIATION: f64 = 350.0;
+pub const DEFAULT_VOLATILITY: f64 = 0.06;
+
+/// System constant constraining volatility change over time. Glickman suggests 0.3 to 1.2.
+const TAU: f64 = 0.5;
+/// Convergence tolerance for the volatility iteration.
+const CONVERGENCE_TOLERANCE: f64 = 0.000001;
+/// Conversion factor between the Glicko scale and the internal Glicko-2 scale.
+const GLICKO2_SCALE: f64 = 173.7178;
+/// Centre of the Glicko scale used by the conversion (independent of `DEFAULT_RATING`).
+const GLICKO_SCALE_CENTER: f64 = 1500.0;
+
+/// A player's Glicko-2 state, expressed on the familiar Glicko (Elo-like) scale.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glicko2Rating {
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+}
+
+impl Default for Glicko2Rating {
+    fn default() -> Self {
+        Glicko2Rating {
+            rating: DEFAULT_RATING,
+            deviation: DEFAULT_DEVIATION,
+            volatility: DEFAULT_VOLATILITY,
+        }
+    }
+}
+
+impl Glicko2Rating {
+    fn mu(&self) -> f64 {
+        (self.rating - GLICKO_SCALE_CENTER) / GLICKO2_SCALE
+    }
+
+    fn phi(&self) -> f64 {
+        self.deviation / GLICKO2_SCALE
+    }
+}
+
+/// Reduces the impact of a game according to the opponent's rating deviation.
+fn g(phi: f64) -> f64 {
+    1.0 / (1.0 + 3.0 * phi * phi / (std::f64::consts::PI * std::f64::consts::PI)).sqrt()
+}
+
+/// Expected score of a player with `mu` against an opponent with `opponent_mu` and `opponent_phi`.
+fn expected_score(mu: f64, opponent_mu: f64, opponent_phi: f64) -> f64 {
+    1.0 / (1.0 + (-g(opponent_phi) * (mu - opponent_mu)).exp())
+}
+
+/// Solves for the new volatility using the Illinois variant of regula falsi (step 5 of Glickman's paper).
+fn new_volatility(phi: f64, volatility: f64, variance: f64, delta: f64) -> f64 {
+    let a = (volatility * volatility).ln();
+    let f = |x: f64| {
+        let ex = x.exp();
+        let denom = phi * phi + variance + ex;
+        ex * (delta * delta - phi * phi - variance - ex) / (2.0 * denom * denom) - (x - a) / (TAU * TAU)
+    };
+
+    let mut lower = a;
+    let mut upper = if delta * delta > phi * phi + variance {
+        (delta * delta - phi * phi - variance).ln()
+    } else {
+        let mut k = 1.0;
+        while f(a - k * TAU) < 0.0 {
+            k += 1.0;
+        }
+        a - k * TAU
+    };
+
+    let mut f_lower = f(lower);
+    let mut f_upper = f(upper);
+    while (upper - lower).abs() > CONVERGENCE_TOLERANCE {
+        let candidate = lower + (lower - upper) * f_lower / (f_upper - f_lower);
+        let f_candidate = f(candidate);
+        if f_candidate * f_upper <= 0.0 {
+            lower = upper;
+            f_lower = f_upper;
+        } else {
+            f_lower /= 2.0;
+        }
+        upper = candidate;
+        f_upper = f_candidate;
+    }
+
+    (lower / 2.0).exp()
+}
+
+/// Applies one rating period to a player.
+///
+/// # Arguments
+/// * `player` - The player's rating at the start of the period.
+/// * `results` - `(opponent_rating, actual_score)` for every game in the period
+///   (1.0 for win, 0.5 for draw, 0.0 for loss). Opponent ratings are their pre-period values.
+///
+/// # Returns
+/// The player's rating at the end of the period. A period without games only grows the deviation.
+pub fn update_rating(player: Glicko2Rating, results: &[(Glicko2Rating, f64)]) -> Glicko2Rating {
+    if results.is_empty() {
+        return inflate_deviation(player, 1);
+    }
+
+    let mu = player.mu();
+    let phi = player.phi();
+
+    let mut variance_inv = 0.0;
+    let mut improvement_sum = 0.0;
+    for (opponent, score) in results {
+        let opponent_g = g(opponent.phi());
+        let expected = expected_score(mu, opponent.mu(), opponent.phi());
+        variance_inv += opponent_g * opponent_g * expected * (1.0 - expected);
+        improvement_sum += opponent_g * (score - expected);
+    }
+    let variance = 1.0 / variance_inv;
+    let delta = variance * improvement_sum;
+
+    let volatility = new_volatility(phi, player.volatility, variance, delta);
+    let phi_star = (phi * phi + volatility * volatility).sqrt();
+    let new_phi = 1.0 / (1.0 / (phi_star * phi_star) + 1.0 / variance).sqrt();
+    let new_mu = mu + new_phi * new_phi * improvement_sum;
+
+    Glicko2Rating {
+        rating: new_mu * GLICKO2_SCALE + GLICKO_SCALE_CENTER,
+        deviation: (new_phi * GLICKO2_SCALE).min(DEFAULT_DEVIATION),
+        volatility,
+    }
+}
+
+/// Grows the rating deviation for `inactive_periods` rating periods without games,
+/// capped at the deviation of a brand-new player.
+pub fn inflate_deviation(player: Glicko2Rating, inactive_periods: u32) -> Glicko2Rating {
+    let phi = player.phi();
+    let grown_phi = (phi * phi + inactive_periods as f64 * player.volatility * player.volatility).sqrt();
+    Glicko2Rating {
+        deviation: (grown_phi * GLICKO2_SCALE).min(DEFAULT_DEVIATION),
+        ..player
+    }
+}
+
+/// The rating as of `now_micros`, with the deviation grown once for every full rating period of
+/// `period_micros` since the last rated game. Never-rated players keep their stored state.
+pub fn rating_at(stored: Glicko2Rating, last_rated_micros: Option<i64>, now_micros: i64, period_micros: i64) -> Glicko2Rating {
+    match last_rated_micros {
+        Some(last) => {
+            let inactive_periods = ((now_micros - last) / period_micros).max(0) as u32;
+            inflate_deviation(stored, inactive_periods)
+        }
+        None => stored,
+    }
+}
+
+/// Season soft reset: pulls `rating` toward the default by `factor` (0.0 keeps it, 1.0 resets
+/// it fully). Deviation and volatility are not part of the reset.
+pub fn soft_reset_rating(rating: f64, factor: f32) -> f64 {
+    DEFAULT_RATING + (rating - DEFAULT_RATING) * (1.0 - factor as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rating(rating: f64, deviation: f64) -> Glicko2Rating {
+        Glicko2Rating { rating, deviation, volatility: DEFAULT_VOLATILITY }
+    }
+
+    #[test]
+    fn test_update_rating_matches_glickman_example() {
+        // Worked example from Glickman's "Example of the Glicko-2 system"
+        let player = rating(1500.0, 200.0);
+        let results = [
+            (rating(1400.0, 30.0), 1.0),
+            (rating(1550.0, 100.0), 0.0),
+            (rating(1700.0, 300.0), 0.0),
+        ];
+        let updated = update_rating(player, &results);
+        assert!((updated.rating - 1464.06).abs() < 0.01, "rating was {}", updated.rating);
+        assert!((updated.deviation - 151.52).abs() < 0.01, "deviation was {}", updated.deviation);
+        assert!((updated.volatility - 0.05999).abs() < 0.00001, "volatility was {}", updated.volatility);
+    }
+
+    #[test]
+    fn test_update_rating_win_against_equal_raises_rating() {
+        let player = Glicko2Rating::default();
+        let updated = update_rating(player, &[(Glicko2Rating::default(), 1.0)]);
+        assert!(updated.rating > player.rating);
+        assert!(updated.deviation < player.deviation);
+    }
+
+    #[test]
+    fn test_update_rating_loss_against_equal_lowers_rating() {
+        let player = Glicko2Rating::default();
+        let updated = update_rating(player, &[(Glicko2Rating::default(), 0.0)]);
+        assert!(updated.rating < player.rating);
+        assert!(updated.deviation < player.deviation, "Any rated game makes the rating more certain");
+    }
+
+    #[test]
+    fn test_update_rating_draw_against_equal_keeps_rating() {
+        let player = rating(1200.0, 80.0);
+        let updated = update_rating(player, &[(rating(1200.0, 80.0), 0.5)]);
+        assert!((updated.rating - player.rating).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_update_rating_without_games_only_grows_deviation() {
+        let player = rating(1350.0, 50.0);
+        let updated = update_rating(player, &[]);
+        assert_eq!(updated.rating, player.rating);
+        assert_eq!(updated.volatility, player.volatility);
+        assert!(updated.deviation > player.deviation);
+    }
+
+    #[test]
+    fn test_inflate_deviation_grows_with_inactivity_and_caps() {
+        let player = rating(1350.0, 50.0);
+        let one_period = inflate_deviation(player, 1);
+        let ten_periods = inflate_deviation(player, 10);
+        assert!(one_period.deviation > player.deviation);
+        assert!(ten_periods.deviation > one_period.deviation);
+        assert_eq!(inflate_deviation(player, 0).deviation, player.deviation);
+        assert_eq!(inflate_deviation(player, 1_000_000).deviation, DEFAULT_DEVIATION);
+    }
+
+    #[test]
+    fn test_rating_at_grows_deviation_per_full_period() {
+        let stored = rating(1350.0, 50.0);
+        assert_eq!(rating_at(stored, None, 1_000, 100), stored, "Never-rated players are not inflated");
+        assert_eq!(rating_at(stored, Some(0), 99, 100), stored, "Less than a full period changes nothing");
+        assert_eq!(rating_at(stored, Some(0), 350, 100), inflate_deviation(stored, 3));
+        assert_eq!(rating_at(stored, Some(500), 0, 100), stored, "A clock running backwards is no inactivity");
+    }
+
+    #[test]
+    fn test_soft_reset_pulls_rating_toward_default() {
+        assert_eq!(soft_reset_rating(1600.0, 0.25), 1500.0);
+        assert_eq!(soft_reset_rating(900.0, 0.0), 900.0);
+        assert_eq!(soft_reset_rating(900.0, 1.0), DEFAULT_RATING);
+    }
+}
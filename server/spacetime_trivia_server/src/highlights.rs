@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+
+const PHOTO_FINISH_MICROS: i64 = 250_000;
+const STAMPEDE_MIN_ANSWERS: usize = 3;
+const STAMPEDE_MIN_SHARE: f32 = 0.6;
+
+/// One answer of a scored round, as the highlight detectors see it.
+#[derive(Clone, Debug)]
+pub struct RoundAnswer<P> {
+    pub player_id: P,
+    pub elo: i32,
+    pub answer_index: u32,
+    pub points: u32,
+    pub submitted_at_micros: i64,
+}
+
+/// Upset: only one correct answer, from the lowest-rated player in the round. Returns that player.
+pub fn upset<P: Copy + PartialEq>(answers: &[RoundAnswer<P>]) -> Option<P> {
+    let mut correct = answers.iter().filter(|a| a.points > 0);
+    let (Some(winner), None) = (correct.next(), correct.next()) else {
+        return None;
+    };
+    let beat_everyone = answers.iter().all(|a| a.player_id == winner.player_id || a.elo > winner.elo);
+    (answers.len() >= 2 && beat_everyone).then_some(winner.player_id)
+}
+
+/// Photo finish: the two fastest correct answers are a hair apart. Returns the faster player and
+/// the two submission times.
+pub fn photo_finish<P: Copy>(answers: &[RoundAnswer<P>]) -> Option<(P, i64, i64)> {
+    let mut correct: Vec<&RoundAnswer<P>> = answers.iter().filter(|a| a.points > 0).collect();
+    correct.sort_by_key(|a| a.submitted_at_micros);
+    match correct.as_slice() {
+        [first, second, ..] if second.submitted_at_micros - first.submitted_at_micros <= PHOTO_FINISH_MICROS => {
+            Some((first.player_id, first.submitted_at_micros, second.submitted_at_micros))
+        }
+        _ => None,
+    }
+}
+
+/// Lightning comeback: the players who were behind the leader before the round and lead after it.
+/// `standings` holds `(player, points_before, points_after)`.
+pub fn lightning_comebacks<P: Copy>(standings: &[(P, u32, u32)]) -> Vec<P> {
+    let leader_before = standings.iter().map(|s| s.1).max().unwrap_or(0);
+    let leader_after = standings.iter().map(|s| s.2).max().unwrap_or(0);
+    standings.iter()
+        .filter(|s| s.1 < leader_before && s.2 == leader_after)
+        .map(|s| s.0)
+        .collect()
+}
+
+/// Crowd stampede: a clear majority of the round chose the same wrong answer. Returns the span from
+/// the first answer of the round to the last answer that joined the stampede.
+pub fn crowd_stampede<P>(answers: &[RoundAnswer<P>]) -> Option<(i64, i64)> {
+    if answers.len() < STAMPEDE_MIN_ANSWERS {
+        return None;
+    }
+    let mut wrong_counts: BTreeMap<u32, usize> = BTreeMap::new();
+    for answer in answers.iter().filter(|a| a.points == 0) {
+        *wrong_counts.entry(answer.answer_index).or_default() += 1;
+    }
+    let (&answer_index, &count) = wrong_counts.iter().max_by_key(|(_, &count)| count)?;
+    if (count as f32 / answers.len() as f32) < STAMPEDE_MIN_SHARE {
+        return None;
+    }
+    let started = answers.iter().map(|a| a.submitted_at_micros).min()?;
+    let ended = answers.iter().filter(|a| a.answer_index == answer_index).map(|a| a.submitted_at_micros).max()?;
+    Some((started, ended))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(player_id: u8, elo: i32, answer_index: u32, points: u32, submitted_at_micros: i64) -> RoundAnswer<u8> {
+        RoundAnswer { player_id, elo, answer_index, points, submitted_at_micros }
+    }
+
+    #[test]
+    fn test_upset_needs_lowest_rated_lone_correct_answer() {
+        let answers = [answer(1, 1000, 0, 10, 1), answer(2, 1200, 2, 0, 2), answer(3, 1300, 2, 0, 3)];
+        assert_eq!(upset(&answers), Some(1));
+
+        let favourite = [answer(1, 1300, 0, 10, 1), answer(2, 1200, 2, 0, 2)];
+        assert_eq!(upset(&favourite), None, "The favourite winning is no upset");
+        let two_correct = [answer(1, 1000, 0, 10, 1), answer(2, 1200, 0, 10, 2), answer(3, 1300, 2, 0, 3)];
+        assert_eq!(upset(&two_correct), None);
+        assert_eq!(upset(&[answer(1, 1000, 0, 10, 1)]), None, "A lone player cannot cause an upset");
+    }
+
+    #[test]
+    fn test_photo_finish_for_near_simultaneous_correct_answers() {
+        let answers = [answer(2, 1200, 0, 10, 1_100_000), answer(1, 1200, 0, 10, 1_000_000), answer(3, 1200, 1, 0, 1_050_000)];
+        assert_eq!(photo_finish(&answers), Some((1, 1_000_000, 1_100_000)));
+
+        let apart = [answer(1, 1200, 0, 10, 0), answer(2, 1200, 0, 10, PHOTO_FINISH_MICROS + 1)];
+        assert_eq!(photo_finish(&apart), None);
+        assert_eq!(photo_finish(&[answer(1, 1200, 0, 10, 0), answer(2, 1200, 1, 0, 0)]), None, "Needs two correct answers");
+    }
+
+    #[test]
+    fn test_lightning_comebacks_take_the_lead() {
+        let standings = [(1, 30, 30), (2, 20, 40), (3, 10, 10)];
+        assert_eq!(lightning_comebacks(&standings), vec![2]);
+        assert!(lightning_comebacks(&[(1, 30, 50), (2, 20, 40)]).is_empty(), "The leader staying ahead is no comeback");
+        assert_eq!(lightning_comebacks(&[(1, 30, 30), (2, 10, 30)]), vec![2], "Drawing level counts");
+    }
+
+    #[test]
+    fn test_crowd_stampede_on_a_shared_wrong_answer() {
+        let answers = [answer(1, 1000, 0, 10, 5), answer(2, 1200, 2, 0, 7), answer(3, 1300, 2, 0, 9)];
+        assert_eq!(crowd_stampede(&answers), Some((5, 9)), "Two of three on the same wrong answer");
+
+        let split = [answer(1, 1000, 0, 10, 5), answer(2, 1200, 2, 0, 7), answer(3, 1300, 3, 0, 9)];
+        assert_eq!(crowd_stampede(&split), None);
+        assert_eq!(crowd_stampede(&answers[1..]), None, "Too few answers");
+    }
+}
@@ -0,0 +1,201 @@
+use std::ops::Bound::{self, Excluded, Included, Unbounded};
+
+const WEEK_MICROS: i64 = 7 * 24 * 60 * 60 * 1_000_000;
+// The Unix epoch was a Thursday; shifting by three days makes weeks start on Monday 00:00 UTC
+const WEEK_START_OFFSET_MICROS: i64 = 3 * 24 * 60 * 60 * 1_000_000;
+
+/// One entry of a leaderboard.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BoardEntry<P> {
+    pub entry_id: u64,
+    pub player_id: P,
+    pub value: i64,
+    pub rank: u32,
+}
+
+/// The operations incremental ranking needs from one board of the leaderboard table, which is
+/// indexed by (board, value) and (board, player_id).
+pub trait LeaderboardStore {
+    type PlayerId: Copy;
+
+    /// The board's entries with a value in `values`.
+    fn entries_with_values(&self, values: (Bound<i64>, Bound<i64>)) -> Vec<BoardEntry<Self::PlayerId>>;
+    /// The lowest-valued entry strictly above `value`.
+    fn lowest_above(&self, value: i64) -> Option<BoardEntry<Self::PlayerId>>;
+    /// How many entries hold exactly `value`.
+    fn count_at(&self, value: i64) -> u32;
+    /// The player's entry, if they are on the board.
+    fn find_player(&self, player_id: Self::PlayerId) -> Option<BoardEntry<Self::PlayerId>>;
+    /// Overwrites an entry's rank.
+    fn set_rank(&mut self, entry_id: u64, rank: u32);
+    /// Moves an existing entry to a new value; its rank is set separately.
+    fn set_value(&mut self, entry_id: u64, value: i64);
+    fn insert(&mut self, player_id: Self::PlayerId, value: i64, rank: u32);
+    fn delete(&mut self, entry_id: u64);
+}
+
+/// Moves the rank of every entry whose value lies in `values` by `rank_delta`.
+fn shift_ranks(store: &mut impl LeaderboardStore, values: (Bound<i64>, Bound<i64>), rank_delta: i32) {
+    for entry in store.entries_with_values(values) {
+        store.set_rank(entry.entry_id, entry.rank.saturating_add_signed(rank_delta));
+    }
+}
+
+/// Competition rank a value would take on the board, not counting an entry already at `value`
+/// that is being placed.
+fn rank_for(store: &impl LeaderboardStore, value: i64) -> u32 {
+    // The next entry above shares its rank with its ties, so we land just below all of them
+    match store.lowest_above(value) {
+        Some(above) => above.rank + store.count_at(above.value),
+        None => 1,
+    }
+}
+
+/// Inserts or moves a player's entry, shifting only the ranks between the old and new value.
+pub fn upsert_entry<S: LeaderboardStore>(store: &mut S, player_id: S::PlayerId, value: i64) {
+    match store.find_player(player_id) {
+        Some(entry) => {
+            if value > entry.value {
+                shift_ranks(store, (Included(entry.value), Excluded(value)), 1);
+            } else if value < entry.value {
+                shift_ranks(store, (Included(value), Excluded(entry.value)), -1);
+            }
+            store.set_value(entry.entry_id, value);
+            let rank = rank_for(store, value);
+            store.set_rank(entry.entry_id, rank);
+        }
+        None => {
+            shift_ranks(store, (Unbounded, Excluded(value)), 1);
+            let rank = rank_for(store, value);
+            store.insert(player_id, value, rank);
+        }
+    }
+}
+
+/// Removes a player's entry, moving everyone below them up one rank.
+pub fn remove_entry<S: LeaderboardStore>(store: &mut S, player_id: S::PlayerId) {
+    if let Some(entry) = store.find_player(player_id) {
+        store.delete(entry.entry_id);
+        shift_ranks(store, (Unbounded, Excluded(entry.value)), -1);
+    }
+}
+
+/// Start of the Monday-to-Sunday UTC week containing `micros`, in microseconds since the Unix epoch.
+pub fn week_start_micros(micros: i64) -> i64 {
+    (micros + WEEK_START_OFFSET_MICROS).div_euclid(WEEK_MICROS) * WEEK_MICROS - WEEK_START_OFFSET_MICROS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ranking::competition_ranks;
+    use std::ops::RangeBounds;
+
+    /// An in-memory stand-in for one board of the leaderboard table.
+    #[derive(Default)]
+    struct MemoryBoard {
+        entries: Vec<BoardEntry<u8>>,
+        next_entry_id: u64,
+    }
+
+    impl MemoryBoard {
+        fn entry_mut(&mut self, entry_id: u64) -> &mut BoardEntry<u8> {
+            self.entries.iter_mut().find(|e| e.entry_id == entry_id).unwrap()
+        }
+
+        /// `(player, rank)` best first, checked against ranking the whole board from scratch.
+        fn ranks(&self) -> Vec<(u8, u32)> {
+            let mut sorted = self.entries.clone();
+            sorted.sort_by_key(|e| (std::cmp::Reverse(e.value), e.player_id));
+            let expected = competition_ranks(&sorted.iter().map(|e| e.value).collect::<Vec<_>>());
+            assert_eq!(sorted.iter().map(|e| e.rank).collect::<Vec<_>>(), expected, "Incremental ranks drifted");
+            sorted.iter().map(|e| (e.player_id, e.rank)).collect()
+        }
+    }
+
+    impl LeaderboardStore for MemoryBoard {
+        type PlayerId = u8;
+
+        fn entries_with_values(&self, values: (Bound<i64>, Bound<i64>)) -> Vec<BoardEntry<u8>> {
+            self.entries.iter().filter(|e| values.contains(&e.value)).cloned().collect()
+        }
+
+        fn lowest_above(&self, value: i64) -> Option<BoardEntry<u8>> {
+            self.entries.iter().filter(|e| e.value > value).min_by_key(|e| e.value).cloned()
+        }
+
+        fn count_at(&self, value: i64) -> u32 {
+            self.entries.iter().filter(|e| e.value == value).count() as u32
+        }
+
+        fn find_player(&self, player_id: u8) -> Option<BoardEntry<u8>> {
+            self.entries.iter().find(|e| e.player_id == player_id).cloned()
+        }
+
+        fn set_rank(&mut self, entry_id: u64, rank: u32) {
+            self.entry_mut(entry_id).rank = rank;
+        }
+
+        fn set_value(&mut self, entry_id: u64, value: i64) {
+            self.entry_mut(entry_id).value = value;
+        }
+
+        fn insert(&mut self, player_id: u8, value: i64, rank: u32) {
+            self.next_entry_id += 1;
+            self.entries.push(BoardEntry { entry_id: self.next_entry_id, player_id, value, rank });
+        }
+
+        fn delete(&mut self, entry_id: u64) {
+            self.entries.retain(|e| e.entry_id != entry_id);
+        }
+    }
+
+    #[test]
+    fn test_upsert_entry_inserts_with_competition_ranks() {
+        let mut board = MemoryBoard::default();
+        upsert_entry(&mut board, 1, 0);
+        upsert_entry(&mut board, 2, 30);
+        upsert_entry(&mut board, 3, 30);
+        assert_eq!(board.ranks(), vec![(2, 1), (3, 1), (1, 3)]);
+    }
+
+    #[test]
+    fn test_upsert_entry_moves_ranks_incrementally() {
+        let mut board = MemoryBoard::default();
+        for (player, value) in [(1, 1200), (2, 1250), (3, 1300), (4, 1250)] {
+            upsert_entry(&mut board, player, value);
+        }
+        assert_eq!(board.ranks(), vec![(3, 1), (2, 2), (4, 2), (1, 4)]);
+
+        upsert_entry(&mut board, 1, 1250);
+        assert_eq!(board.ranks(), vec![(3, 1), (1, 2), (2, 2), (4, 2)], "Moving up into a tie shares the rank");
+        upsert_entry(&mut board, 3, 1100);
+        assert_eq!(board.ranks(), vec![(1, 1), (2, 1), (4, 1), (3, 4)], "Dropping past everyone moves them all up");
+        upsert_entry(&mut board, 4, 1400);
+        upsert_entry(&mut board, 4, 1400);
+        assert_eq!(board.ranks(), vec![(4, 1), (1, 2), (2, 2), (3, 4)]);
+    }
+
+    #[test]
+    fn test_remove_entry_moves_lower_entries_up() {
+        let mut board = MemoryBoard::default();
+        for (player, value) in [(1, 10), (2, 20), (3, 20), (4, 30)] {
+            upsert_entry(&mut board, player, value);
+        }
+        remove_entry(&mut board, 2);
+        assert_eq!(board.ranks(), vec![(4, 1), (3, 2), (1, 3)]);
+        remove_entry(&mut board, 9);
+        assert_eq!(board.entries.len(), 3, "Removing a player who is not on the board is a no-op");
+    }
+
+    #[test]
+    fn test_week_start_micros_is_monday_midnight_utc() {
+        const DAY: i64 = 24 * 60 * 60 * 1_000_000;
+        // 2026-10-12 was a Monday, 20738 days after the epoch
+        let monday = 20738 * DAY;
+        assert_eq!(week_start_micros(monday), monday);
+        assert_eq!(week_start_micros(monday + 6 * DAY + DAY - 1), monday, "Sunday 23:59 is still the same week");
+        assert_eq!(week_start_micros(monday + 7 * DAY), monday + 7 * DAY);
+        assert_eq!(week_start_micros(0), -3 * DAY, "The epoch's week started on Monday 1969-12-29");
+    }
+}
@@ -1,23 +1,120 @@
+pub mod chat;
+pub mod crowd_meter;
 pub mod elo;
-
-use spacetimedb::{Identity, ReducerContext, Timestamp, Table, log, table, reducer};
-use crate::elo::calculate_elo_delta;
+pub mod game_modes;
+pub mod glicko2;
+pub mod highlights;
+pub mod leaderboard;
+pub mod profile;
+pub mod moderation;
+pub mod questions;
+pub mod ranking;
+pub mod roster;
+pub mod scoring;
+pub mod settlement;
+pub mod word_filter;
+
+use spacetimedb::{client_visibility_filter, Filter, Identity, ReducerContext, ScheduleAt, TimeDuration, Timestamp, Table, log, table, reducer};
+use crate::chat::{count_message, prepare_message, retention_cutoff_micros, RateWindow};
+use crate::crowd_meter::{change_event, counts_by_index, on_answer, parse_mode, percent_of, public_views, record_answer, CrowdMeterStore, MeterView, OnAnswer, CROWD_METER_LIVE};
+use crate::elo::{
+    are_opponents, calculate_multiplayer_elo_deltas_with_k, calibrate_item, difficulty_band, is_provisional,
+    item_rating_for_label, item_rating_range_for_success, k_factor_for, opponents_average_elo, pairwise_outcome, soft_reset_elo, team_rating,
+    DIFFICULTY_MEDIUM, INITIAL_ELO, ITEM_CALIBRATION_ANSWERS,
+};
+use crate::game_modes::{
+    buzz_outcome, draft_teams, parse_team_settings, royale_result, royale_round_outcome, team_round_points, validate_royale_settings, MIN_TEAM_COUNT,
+    TEAM_SCORING_SUM,
+};
+use crate::glicko2::Glicko2Rating;
+use crate::highlights::{crowd_stampede, lightning_comebacks, photo_finish, upset, RoundAnswer};
+use crate::leaderboard::{remove_entry, upsert_entry, week_start_micros, BoardEntry, LeaderboardStore};
+use crate::moderation::{authorize, expiry_micros_after, guest_ban_prevails, is_in_force, mute_covers, mutes_in_force, validate_reason, ModerationRequest};
+use crate::profile::{validate_avatar, validate_player_name, DEFAULT_AVATAR};
+use crate::questions::{adaptive_choices, fixed_choices, mixed_choices, parse_difficulty, skip_played, Candidate};
+use crate::ranking::competition_ranks;
+use crate::roster::{
+    after_host_grace_period, after_leaving, live_join, on_host_disconnect, resume_point, seats_to_take_over, seats_to_vacate,
+    HostChange, LiveJoin, Seat,
+};
+use crate::scoring::{add_round, correct_by_speed, merge_lines, padded_counts, ScoreLine};
+use crate::settlement::{merge_season_lines, settled_players, team_member_deltas, topic_contests, SeasonLine};
 
 // Status enums as string constants
 const LOBBY_STATUS_WAITING: &str = "waiting";
 const LOBBY_STATUS_IN_GAME: &str = "in_game";
 const LOBBY_STATUS_FINISHED: &str = "finished";
+const LOBBY_STATUS_ABANDONED: &str = "abandoned"; // Everyone left before the game finished
+
+const MEMBER_ROLE_PLAYER: &str = "player";
+const MEMBER_ROLE_SPECTATOR: &str = "spectator"; // Watches rounds and the crowd meter; cannot answer and is not rated
+
+const GAME_MODE_CLASSIC: &str = "classic"; // Every player for themselves
+const GAME_MODE_TEAMS: &str = "teams"; // Players are split into teams balanced by Elo and rated on team results
+const GAME_MODE_BATTLE_ROYALE: &str = "battle_royale"; // Wrong or missing answers cost lives; the last player standing wins
+const GAME_MODE_BUZZER: &str = "buzzer"; // The first correct answer takes the round; a wrong one locks its player out
 
 const ROUND_STATUS_WAITING: &str = "waiting";
 const ROUND_STATUS_IN_PROGRESS: &str = "in_progress";
 const ROUND_STATUS_SCORING: &str = "scoring";
 const ROUND_STATUS_FINISHED: &str = "finished";
 
+const GAME_EVENT_ROUND_OPENED: &str = "round_opened"; // value: question id
+const GAME_EVENT_LIGHTNING_TRIGGERED: &str = "lightning_triggered";
+const GAME_EVENT_ANSWER_SUBMITTED: &str = "answer_submitted"; // No player or choice until scoring: live and fog meter changes land in the same transaction
+const GAME_EVENT_CROWD_METER_CHANGED: &str = "crowd_meter_changed"; // answer_index, value: published count (percent in fog mode)
+const GAME_EVENT_ANSWER_SCORED: &str = "answer_scored"; // player, answer_index, value: points
+const GAME_EVENT_ROUND_SCORED: &str = "round_scored"; // value: number of correct answers
+const GAME_EVENT_PLAYER_ELIMINATED: &str = "player_eliminated"; // player, value: final placement
+const GAME_EVENT_COMBO_AWARDED: &str = "combo_awarded"; // player, value: streak length
+
+const HIGHLIGHT_UPSET: &str = "upset"; // The lowest-rated player was the only one to answer correctly
+const HIGHLIGHT_PHOTO_FINISH: &str = "photo_finish"; // The two fastest correct answers arrived almost together
+const HIGHLIGHT_LIGHTNING_COMEBACK: &str = "lightning_comeback"; // A lightning round took a trailing player into the lead
+const HIGHLIGHT_CROWD_STAMPEDE: &str = "crowd_stampede"; // Most of the lobby piled onto the same wrong answer
+const HIGHLIGHT_SCAN_INTERVAL_MICROS: i64 = 10 * 1_000_000;
+
 const AGENT_JOB_STATUS_PENDING: &str = "pending";
 const AGENT_JOB_STATUS_PROCESSING: &str = "processing"; // Optional intermediate status
 const AGENT_JOB_STATUS_COMPLETED: &str = "completed";
 const AGENT_JOB_STATUS_FAILED: &str = "failed";
 
+const RATING_SYSTEM_ELO: &str = "elo";
+const RATING_SYSTEM_GLICKO2: &str = "glicko2";
+const RATING_CONFIG_ID: u32 = 0; // The rating_config table holds a single row
+const QUESTION_MODE_ADAPTIVE: &str = "adaptive"; // Target the lobby's skill level
+const QUESTION_MODE_FIXED: &str = "fixed"; // Only one difficulty band
+const QUESTION_MODE_MIXED: &str = "mixed"; // Rotate Easy, Medium, Hard round by round
+// Adaptive mode picks questions the lobby's average player answers correctly 40-70% of the time
+const ADAPTIVE_MIN_SUCCESS: f32 = 0.4;
+const ADAPTIVE_MAX_SUCCESS: f32 = 0.7;
+
+const LEADERBOARD_RATING: &str = "rating"; // All-time rating from the active rating system
+const LEADERBOARD_WEEKLY_PREFIX: &str = "weekly:"; // Followed by the week's Monday, e.g. "weekly:2026-10-12"
+const LEADERBOARD_TOPIC_PREFIX: &str = "topic:"; // Followed by the topic name
+
+// Issuer of the tokens a local SpacetimeDB mints for anonymous connections. Seeded into
+// guest_token_issuer at init; admins add the issuer of their own host there.
+const DEFAULT_GUEST_TOKEN_ISSUER: &str = "localhost";
+// How long a guest's request to be merged into an account stays valid
+const ACCOUNT_LINK_TTL_MICROS: i64 = 10 * 60 * 1_000_000;
+
+const MODERATION_ACTION_KICK: &str = "kick";
+const MODERATION_ACTION_MUTE: &str = "mute";
+const MODERATION_ACTION_UNMUTE: &str = "unmute";
+const MODERATION_ACTION_BAN: &str = "ban";
+const MODERATION_ACTION_UNBAN: &str = "unban";
+const MODERATION_ACTION_GRANT_MODERATOR: &str = "grant_moderator";
+const MODERATION_ACTION_REVOKE_MODERATOR: &str = "revoke_moderator";
+
+const CHAT_PURGE_INTERVAL_MICROS: i64 = 60 * 60 * 1_000_000;
+
+// How long a disconnected player keeps their seat in a waiting lobby before being removed
+const DISCONNECT_GRACE_PERIOD_MICROS: i64 = 60 * 1_000_000;
+
+// Length of a Glicko-2 rating period; RD grows once per full period without a rated game
+const GLICKO_RATING_PERIOD_MICROS: i64 = 24 * 60 * 60 * 1_000_000;
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize, spacetimedb::SpacetimeType)] // Added spacetimedb::SpacetimeType
 pub struct NewQuestionData {
     text: String,
@@ -44,6 +141,15 @@ pub struct Question {
     difficulty: String,
     quality_score: i32,
     origin_agent: Option<String>,
+    #[index(btree)]
+    rating: i32, // Item rating, calibrated from answers as if each one were a player-vs-question match
+    rated_answers: u32, // Answers that have contributed to `rating`
+}
+
+impl Candidate for Question {
+    fn question_id(&self) -> u64 { self.question_id }
+    fn rating(&self) -> i32 { self.rating }
+    fn difficulty(&self) -> &str { &self.difficulty }
 }
 
 #[table(name = player, public)]
@@ -54,8 +160,14 @@ pub struct Player {
     #[unique]  // Ensure unique player names
     #[index(btree)]  // Add index for name lookups
     name: String,
-    score: u32,
+    score: u32, // Lifetime points
+    season_score: u32, // Points since the current season opened; archived and cleared by close_season
     elo: i32, // New field for Elo rating, default to 1200
+    games_played: u32, // Rated games settled so far
+    provisional: bool, // True for the first PROVISIONAL_GAME_COUNT rated games; hidden from leaderboards
+    avatar: String, // One of AVATARS
+    online: bool, // Maintained by the connect/disconnect lifecycle reducers
+    last_seen: Timestamp, // Last connect, disconnect or join
 }
 
 #[table(name = lobby, public)]
@@ -67,8 +179,79 @@ pub struct Lobby {
     name: Option<String>,
     #[index(btree)]  // Add index for status filtering
     status: String,
+    #[index(btree)]
     host_id: Identity,
     next_round_is_lightning: bool,
+    topic: Option<String>, // Topic lobbies only draw questions from, and matchmake on, this topic
+    question_mode: String, // "adaptive", "fixed" or "mixed"
+    fixed_difficulty: Option<String>, // Difficulty band used in "fixed" mode
+    crowd_meter_mode: String, // One of the CROWD_METER_* modes
+    crowd_meter_delay_secs: u32, // Only used in "delayed" mode
+    game_mode: String, // One of the GAME_MODE_* modes
+    team_count: u32, // Only used in "teams" mode
+    team_scoring: String, // One of the TEAM_SCORING_* policies, only used in "teams" mode
+    royale_lives: u32, // Lives each player starts with, only used in "battle_royale" mode
+    royale_round_cap: Option<u32>, // Battle royale ends after this many rounds even with several survivors
+}
+
+#[table(name = lobby_member, public)]
+#[derive(Clone, Debug)]
+pub struct LobbyMember {
+    #[primary_key]
+    #[auto_inc]
+    member_id: u64,
+    #[index(btree)]
+    lobby_id: u64,
+    #[index(btree)]
+    player_id: Identity,
+    joined_at: Timestamp,
+    role: String, // "player" or "spectator"
+    joins_next_round: bool, // Spectator asked to play; becomes a player when the next round starts
+}
+
+// A team in a "teams" lobby, created when the game starts
+#[table(name = lobby_team, public)]
+#[derive(Clone, Debug)]
+pub struct LobbyTeam {
+    #[primary_key]
+    #[auto_inc]
+    team_id: u64,
+    #[index(btree)]
+    lobby_id: u64,
+    team_number: u32, // 1-based, for display
+    captain_id: Identity, // Highest-rated member when the teams were drawn
+    rating: i32, // Mean Elo of the members
+    points: u32, // Running team score under the lobby's team scoring policy
+}
+
+// A player's run in a battle royale, created when the game starts
+#[table(name = royale_standing, public)]
+#[derive(Clone, Debug)]
+pub struct RoyaleStanding {
+    #[primary_key]
+    #[auto_inc]
+    standing_id: u64,
+    #[index(btree)]
+    lobby_id: u64,
+    #[index(btree)]
+    player_id: Identity,
+    lives: u32,
+    placement: Option<u32>, // None while still in the game; players out in the same round share one
+    eliminated_in_round: Option<u64>,
+}
+
+#[table(name = team_member, public)]
+#[derive(Clone, Debug)]
+pub struct TeamMember {
+    #[primary_key]
+    #[auto_inc]
+    team_member_id: u64,
+    #[index(btree)]
+    lobby_id: u64,
+    #[index(btree)]
+    team_id: u64,
+    #[index(btree)]
+    player_id: Identity,
 }
 
 #[table(name = active_round, public)]
@@ -86,58 +269,1543 @@ pub struct ActiveRound {
     is_lightning: bool,
 }
 
-#[table(name = answer, public)]
-#[derive(Clone, Debug)] // Reverted: Removed Table
-pub struct Answer {
-    #[primary_key]
-    #[auto_inc]
-    answer_id: u64,
-    #[index(btree)]
-    round_id: u64,
-    #[index(btree)]  // Add index for player filtering
-    player_id: Identity,
-    chosen_answer_index: u32,
-    score: Option<u32>,
+// Private: a public answer table would reveal every choice before scoring, whatever the crowd meter mode
+#[table(name = answer, index(name = round_player, btree(columns = [round_id, player_id])))]
+#[derive(Clone, Debug)] // Reverted: Removed Table
+pub struct Answer {
+    #[primary_key]
+    #[auto_inc]
+    answer_id: u64,
+    #[index(btree)]
+    round_id: u64,
+    #[index(btree)]  // Add index for player filtering
+    player_id: Identity,
+    chosen_answer_index: u32,
+    score: Option<u32>,
+    submitted_at: Timestamp, // Server time the answer arrived; orders answers for speed and replays
+}
+
+// Everything a client needs to show the outcome of a scored round, written by score_round
+#[table(name = round_result, public)]
+#[derive(Clone, Debug)]
+pub struct RoundResult {
+    #[primary_key]
+    round_id: u64,
+    #[index(btree)]
+    lobby_id: u64,
+    question_id: u64,
+    correct_answer_index: u32,
+    choice_counts: Vec<u32>, // Answers per choice, one entry for every choice of the question
+    correct_players: Vec<Identity>, // Fastest first
+    points_awarded: Vec<u32>, // Parallel to correct_players
+    scored_at: Timestamp,
+}
+
+// A player's score in one lobby's game, updated in the same transaction as each round's scoring.
+// In-game ranking and Elo settlement read this; Player.score only keeps lifetime points.
+#[table(name = lobby_score, public, index(name = lobby_player, btree(columns = [lobby_id, player_id])))]
+#[derive(Clone, Debug)]
+pub struct LobbyScore {
+    #[primary_key]
+    #[auto_inc]
+    score_id: u64,
+    lobby_id: u64,
+    player_id: Identity,
+    player_name: String,
+    points: u32,
+    correct_count: u32,
+    streak: u32, // Consecutive rounds answered correctly, reset by a wrong or missing answer
+    rank: u32, // Competition ranking by points within the lobby
+    updated_at: Timestamp,
+}
+
+// Append-only log of everything that happens in a lobby's rounds, in order of event_id
+#[table(name = game_event, public)]
+#[derive(Clone, Debug)]
+pub struct GameEvent {
+    #[primary_key]
+    #[auto_inc]
+    event_id: u64,
+    #[index(btree)]
+    lobby_id: u64,
+    #[index(btree)]
+    round_id: u64,
+    kind: String, // One of the GAME_EVENT_* constants
+    player_id: Option<Identity>,
+    answer_index: Option<u32>,
+    value: Option<i64>, // Meaning depends on `kind`
+    occurred_at: Timestamp,
+}
+
+#[table(name = highlight, public)]
+#[derive(Clone, Debug)]
+pub struct Highlight {
+    #[primary_key]
+    #[auto_inc]
+    highlight_id: u64,
+    #[index(btree)]
+    lobby_id: u64,
+    #[index(btree)]
+    round_id: u64,
+    kind: String, // One of the HIGHLIGHT_* constants
+    player_id: Option<Identity>, // The player the moment belongs to, if any
+    started_at: Timestamp, // Time range to replay from the game event log
+    ended_at: Timestamp,
+    detected_at: Timestamp,
+}
+
+// Rounds scored since detect_highlights last ran
+#[table(name = pending_highlight_scan)]
+#[derive(Clone, Debug)]
+pub struct PendingHighlightScan {
+    #[primary_key]
+    round_id: u64,
+    scored_at: Timestamp,
+}
+
+// Periodic timer for detect_highlights
+#[table(name = highlight_schedule, scheduled(detect_highlights))]
+#[derive(Clone, Debug)]
+pub struct HighlightSchedule {
+    #[primary_key]
+    #[auto_inc]
+    scheduled_id: u64,
+    scheduled_at: ScheduleAt,
+}
+
+#[table(name = agent_job_queue, public)]
+#[derive(Clone, Debug)] // Reverted: Removed Table
+pub struct AgentJobQueue {
+    #[primary_key]
+    #[auto_inc]
+    job_id: u64,            // Corresponds to id PK(auto_inc) in SPECS
+    agent_id: u64,          // ID of the agent to perform the work
+    payload_json: String,   // The actual work payload, e.g., topic info for question generation
+    #[index(btree)]
+    status: String,         // e.g., "pending", "processing", "completed", "failed"
+    error_message: Option<String>, // Optional: To store error details if the job failed
+    // created_at: Timestamp, // Optional: consider adding for tracking
+    // updated_at: Timestamp, // Optional: consider adding for tracking
+}
+
+#[table(name = agent_registry, public)]
+#[derive(Clone, Debug)] // Reverted: Removed Table
+pub struct AgentRegistry {
+    #[primary_key]
+    #[auto_inc]
+    agent_id: u64,          // Corresponds to id PK in SPECS
+    owner_id: Identity,       // The Identity of the user who registered the agent
+    wasm_hash: String,        // SHA256 hash of the agent's WASM binary
+    capabilities: Vec<String>,// List of agent capabilities (e.g., "generate_questions")
+    energy_quota: u64,        // Resource limit for the agent
+    // registered_at: Timestamp, // Optional: for tracking registration time
+    // name: Option<String>,     // Optional: a human-readable name for the agent
+    // description: Option<String>, // Optional: a short description
+}
+
+// Private ground truth; clients read the crowd_meter_view tables, filled according to the lobby's mode
+#[table(name = crowd_meter_stats, index(name = round_answer, btree(columns = [round_id, answer_index])))]
+#[derive(Clone, Debug)]
+pub struct CrowdMeterStats {
+    #[primary_key]
+    #[auto_inc]
+    stat_id: u64,
+    round_id: u64,
+    answer_index: u32, // The index of the answer chosen (e.g., 0, 1, 2, 3)
+    count: u32,        // Number of players who chose this answer_index for this round
+}
+
+// What everyone in the lobby is allowed to see of the crowd meter
+#[table(name = crowd_meter_view, public, index(name = round_answer, btree(columns = [round_id, answer_index])))]
+#[derive(Clone, Debug)]
+pub struct CrowdMeterView {
+    #[primary_key]
+    #[auto_inc]
+    view_id: u64,
+    round_id: u64,
+    answer_index: u32,
+    count: Option<u32>, // None in fog mode
+    percent: u8, // Share of all answers published so far
+    updated_at: Timestamp,
+}
+
+// Per-player snapshot for "after_answer" mode, taken when the viewer answered.
+// Only visible to its viewer, see CROWD_METER_ANSWERED_VIEW_OWN_ROWS.
+#[table(name = crowd_meter_answered_view, public)]
+#[derive(Clone, Debug)]
+pub struct CrowdMeterAnsweredView {
+    #[primary_key]
+    #[auto_inc]
+    view_id: u64,
+    #[index(btree)]
+    round_id: u64,
+    #[index(btree)]
+    viewer_id: Identity,
+    answer_index: u32,
+    count: u32,
+    percent: u8,
+    snapshot_at: Timestamp,
+}
+
+// Diffing two players' consecutive snapshots would reveal the later player's choice
+#[client_visibility_filter]
+const CROWD_METER_ANSWERED_VIEW_OWN_ROWS: Filter = Filter::Sql(
+    "SELECT * FROM crowd_meter_answered_view WHERE viewer_id = :sender"
+);
+
+// One-shot timer publishing a crowd meter snapshot in "delayed" mode
+#[table(name = crowd_meter_release, scheduled(release_crowd_meter))]
+#[derive(Clone, Debug)]
+pub struct CrowdMeterRelease {
+    #[primary_key]
+    #[auto_inc]
+    scheduled_id: u64,
+    scheduled_at: ScheduleAt,
+    round_id: u64,
+    counts: Vec<u32>, // Count per answer index when the snapshot was taken
+}
+
+#[table(
+    name = leaderboard_entry,
+    public,
+    index(name = board_value, btree(columns = [board, value])),
+    index(name = board_rank, btree(columns = [board, rank])),
+    index(name = board_player, btree(columns = [board, player_id]))
+)]
+#[derive(Clone, Debug)]
+pub struct LeaderboardEntry {
+    #[primary_key]
+    #[auto_inc]
+    entry_id: u64,
+    board: String, // "rating", "weekly:<monday>" or "topic:<topic>"
+    #[index(btree)]
+    player_id: Identity,
+    player_name: String,
+    value: i64,
+    rank: u32, // Competition ranking: 1 + number of entries on the board with a higher value
+    updated_at: Timestamp,
+}
+
+#[table(name = admin)]
+#[derive(Clone, Debug)]
+pub struct Admin {
+    #[primary_key]
+    admin_id: Identity,
+    granted_at: Timestamp,
+}
+
+// Which rating drives matchmaking and leaderboards; both systems are updated on every settlement.
+// Without a row the module uses Elo.
+#[table(name = rating_config, public)]
+#[derive(Clone, Debug)]
+pub struct RatingConfig {
+    #[primary_key]
+    config_id: u32, // Always RATING_CONFIG_ID
+    rating_system: String, // RATING_SYSTEM_ELO or RATING_SYSTEM_GLICKO2
+    updated_by: Identity,
+    updated_at: Timestamp,
+}
+
+// Token issuers whose identities count as guests; tokens from any other issuer are signed-in accounts
+#[table(name = guest_token_issuer)]
+#[derive(Clone, Debug)]
+pub struct GuestTokenIssuer {
+    #[primary_key]
+    issuer: String,
+    added_by: Identity,
+    added_at: Timestamp,
+}
+
+// Where a player currently is, so a reconnecting client knows which lobby and round to resubscribe to.
+// Only visible to its own player, see PLAYER_SESSION_OWN_ROW.
+#[table(name = player_session, public)]
+#[derive(Clone, Debug)]
+pub struct PlayerSession {
+    #[primary_key]
+    player_id: Identity,
+    lobby_id: Option<u64>, // None when the player is not in an open lobby
+    round_id: Option<u64>, // Latest round of that lobby, None before the game starts
+    updated_at: Timestamp,
+}
+
+// Other clients have no need to track where a player is or when they were last active
+#[client_visibility_filter]
+const PLAYER_SESSION_OWN_ROW: Filter = Filter::Sql(
+    "SELECT * FROM player_session WHERE player_id = :sender"
+);
+
+// One-shot timer that removes a disconnected player from waiting lobbies once the grace period is over
+#[table(name = lobby_departure, scheduled(remove_disconnected_player))]
+#[derive(Clone, Debug)]
+pub struct LobbyDeparture {
+    #[primary_key]
+    #[auto_inc]
+    scheduled_id: u64,
+    scheduled_at: ScheduleAt,
+    #[index(btree)]
+    player_id: Identity,
+}
+
+// A guest asking to have their stats merged into a signed-in account
+#[table(name = account_link_request)]
+#[derive(Clone, Debug)]
+pub struct AccountLinkRequest {
+    #[primary_key]
+    guest_id: Identity,
+    account_id: Identity,
+    requested_at: Timestamp,
+}
+
+#[table(name = moderator, public)]
+#[derive(Clone, Debug)]
+pub struct Moderator {
+    #[primary_key]
+    moderator_id: Identity,
+    granted_by: Identity,
+    granted_at: Timestamp,
+}
+
+#[table(name = player_ban, public)]
+#[derive(Clone, Debug)]
+pub struct PlayerBan {
+    #[primary_key]
+    player_id: Identity,
+    banned_by: Identity,
+    reason: String,
+    banned_at: Timestamp,
+    expires_at: Option<Timestamp>, // None for a permanent ban
+}
+
+#[table(name = player_mute, public)]
+#[derive(Clone, Debug)]
+pub struct PlayerMute {
+    #[primary_key]
+    #[auto_inc]
+    mute_id: u64,
+    #[index(btree)]
+    player_id: Identity,
+    lobby_id: Option<u64>, // None mutes the player everywhere
+    muted_by: Identity,
+    muted_at: Timestamp,
+    expires_at: Option<Timestamp>, // None until lifted
+}
+
+#[table(name = chat_message, public)]
+#[derive(Clone, Debug)]
+pub struct ChatMessage {
+    #[primary_key]
+    #[auto_inc]
+    message_id: u64,
+    #[index(btree)]
+    lobby_id: u64,
+    sender_id: Identity,
+    sender_name: String,
+    text: String, // Already passed through the word filter
+    sent_at: Timestamp,
+    #[index(btree)]
+    sent_at_micros: i64, // sent_at as an indexable value, so purge_old_chat can range-delete
+}
+
+// Per-player chat rate limiting window
+#[table(name = chat_rate_limit)]
+#[derive(Clone, Debug)]
+pub struct ChatRateLimit {
+    #[primary_key]
+    player_id: Identity,
+    window_started_at: Timestamp,
+    messages_in_window: u32,
+}
+
+// Periodic timer that deletes chat past the retention window in chat.rs
+#[table(name = chat_purge_schedule, scheduled(purge_old_chat))]
+#[derive(Clone, Debug)]
+pub struct ChatPurgeSchedule {
+    #[primary_key]
+    #[auto_inc]
+    scheduled_id: u64,
+    scheduled_at: ScheduleAt,
+}
+
+// Append-only record of every moderation action. Private: reasons can name other players.
+#[table(name = moderation_audit)]
+#[derive(Clone, Debug)]
+pub struct ModerationAudit {
+    #[primary_key]
+    #[auto_inc]
+    audit_id: u64,
+    #[index(btree)]
+    actor_id: Identity,
+    #[index(btree)]
+    target_id: Identity,
+    action: String, // One of the MODERATION_ACTION_* constants
+    reason: String,
+    lobby_id: Option<u64>, // Set for lobby-scoped actions
+    expires_at: Option<Timestamp>,
+    created_at: Timestamp,
+}
+
+#[table(name = season, public)]
+#[derive(Clone, Debug)]
+pub struct Season {
+    #[primary_key]
+    #[auto_inc]
+    season_id: u64,
+    name: String,
+    started_at: Timestamp,
+    ended_at: Option<Timestamp>, // None while the season is open
+    soft_reset_factor: Option<f32>, // Reset applied when the season closed
+}
+
+#[table(name = season_standing, public)]
+#[derive(Clone, Debug)]
+pub struct SeasonStanding {
+    #[primary_key]
+    #[auto_inc]
+    standing_id: u64,
+    #[index(btree)]
+    season_id: u64,
+    #[index(btree)]
+    player_id: Identity,
+    player_name: String,
+    final_elo: i32,
+    rank: u32, // Competition ranking: equal Elo shares a rank
+    games_played: u32,
+    provisional: bool,
+    score: u32, // Points accumulated during the season
+}
+
+#[table(name = rating_history, public)]
+#[derive(Clone, Debug)]
+pub struct RatingHistory {
+    #[primary_key]
+    #[auto_inc]
+    history_id: u64,
+    #[index(btree)]
+    player_id: Identity,
+    #[index(btree)]
+    lobby_id: u64,
+    elo_before: i32,
+    elo_after: i32,
+    delta: i32,
+    opponents_average_elo: i32, // Average pre-game Elo of everyone else settled in the lobby, teammates excluded
+    recorded_at: Timestamp,
+}
+
+#[table(name = topic_rating, public, index(name = player_topic, btree(columns = [player_id, topic])))]
+#[derive(Clone, Debug)]
+pub struct TopicRating {
+    #[primary_key]
+    #[auto_inc]
+    topic_rating_id: u64,
+    player_id: Identity,
+    #[index(btree)]
+    topic: String,
+    elo: i32,
+    games_played: u32, // Rated games that included at least one round of this topic
+}
+
+#[table(name = player_glicko, public)]
+#[derive(Clone, Debug)]
+pub struct PlayerGlicko {
+    #[primary_key]
+    player_id: Identity,
+    rating: f64,
+    deviation: f64,
+    volatility: f64,
+    last_rated_at: Option<Timestamp>, // None until the first rated game
+}
+
+impl PlayerGlicko {
+    fn new(player_id: Identity) -> Self {
+        let initial = Glicko2Rating::default();
+        PlayerGlicko {
+            player_id,
+            rating: initial.rating,
+            deviation: initial.deviation,
+            volatility: initial.volatility,
+            last_rated_at: None,
+        }
+    }
+
+    /// The Glicko-2 state as of `now`, with RD grown for every full rating period since the last rated game.
+    fn rating_at(&self, now: Timestamp) -> Glicko2Rating {
+        let stored = Glicko2Rating { rating: self.rating, deviation: self.deviation, volatility: self.volatility };
+        glicko2::rating_at(
+            stored,
+            self.last_rated_at.map(|last| last.to_micros_since_unix_epoch()),
+            now.to_micros_since_unix_epoch(),
+            GLICKO_RATING_PERIOD_MICROS,
+        )
+    }
+}
+
+/// The player's rating for `topic`, or a fresh one seeded from their global Elo if they have never played it.
+fn topic_rating_for(ctx: &ReducerContext, player_id: Identity, topic: &str) -> TopicRating {
+    ctx.db.topic_rating().player_topic().filter((player_id, topic)).next()
+        .unwrap_or_else(|| TopicRating {
+            topic_rating_id: 0,
+            player_id,
+            topic: topic.to_string(),
+            elo: ctx.db.player().player_id().find(player_id).map(|p| p.elo).unwrap_or(INITIAL_ELO),
+            games_played: 0,
+        })
+}
+
+/// The rating system an admin selected with set_rating_system, Elo until one is chosen.
+fn active_rating_system(ctx: &ReducerContext) -> String {
+    ctx.db.rating_config().config_id().find(RATING_CONFIG_ID)
+        .map(|config| config.rating_system)
+        .unwrap_or_else(|| RATING_SYSTEM_ELO.to_string())
+}
+
+/// Rating used for matchmaking and leaderboards. Topic lobbies and boards use the per-topic Elo;
+/// otherwise the rating comes from the active rating system.
+fn active_rating(ctx: &ReducerContext, player_id: Identity, topic: Option<&str>) -> i32 {
+    if let Some(topic) = topic {
+        return topic_rating_for(ctx, player_id, topic).elo;
+    }
+    if active_rating_system(ctx) == RATING_SYSTEM_GLICKO2 {
+        let glicko = ctx.db.player_glicko().player_id().find(player_id)
+            .unwrap_or_else(|| PlayerGlicko::new(player_id));
+        return glicko.rating.round() as i32;
+    }
+    ctx.db.player().player_id().find(player_id)
+        .map(|p| p.elo)
+        .unwrap_or(INITIAL_ELO)
+}
+
+/// Adds the player to the lobby's roster with `role` unless they are already on it.
+fn add_lobby_member(ctx: &ReducerContext, lobby_id: u64, player_id: Identity, role: &str) {
+    if ctx.db.lobby_member().lobby_id().filter(lobby_id).any(|m| m.player_id == player_id) {
+        return;
+    }
+    ctx.db.lobby_member().insert(LobbyMember {
+        member_id: 0,
+        lobby_id,
+        player_id,
+        joined_at: ctx.timestamp,
+        role: role.to_string(),
+        joins_next_round: false,
+    });
+    let latest_round = ctx.db.active_round().lobby_id().filter(lobby_id).map(|r| r.round_id).max();
+    set_player_session(ctx, player_id, Some(lobby_id), latest_round);
+}
+
+/// Records which lobby and round the player is in.
+fn set_player_session(ctx: &ReducerContext, player_id: Identity, lobby_id: Option<u64>, round_id: Option<u64>) {
+    let session = PlayerSession { player_id, lobby_id, round_id, updated_at: ctx.timestamp };
+    if ctx.db.player_session().player_id().find(player_id).is_some() {
+        ctx.db.player_session().player_id().update(session);
+    } else {
+        ctx.db.player_session().insert(session);
+    }
+}
+
+/// Points every member's session at the lobby's new round.
+fn advance_lobby_sessions(ctx: &ReducerContext, lobby_id: u64, round_id: u64) {
+    for member in ctx.db.lobby_member().lobby_id().filter(lobby_id) {
+        set_player_session(ctx, member.player_id, Some(lobby_id), Some(round_id));
+    }
+}
+
+/// Seats the spectators who asked to join live. Called at every round boundary.
+fn promote_waiting_spectators(ctx: &ReducerContext, lobby_id: u64) {
+    let joining: Vec<LobbyMember> = ctx.db.lobby_member().lobby_id().filter(lobby_id)
+        .filter(|m| m.joins_next_round)
+        .collect();
+    for mut member in joining {
+        log::info!("Spectator {} joins lobby {} as a player", member.player_id, lobby_id);
+        member.role = MEMBER_ROLE_PLAYER.to_string();
+        member.joins_next_round = false;
+        ctx.db.lobby_member().member_id().update(member);
+    }
+}
+
+/// True if the player is watching the lobby rather than playing in it.
+fn is_spectator(ctx: &ReducerContext, lobby_id: u64, player_id: Identity) -> bool {
+    ctx.db.lobby_member().lobby_id().filter(lobby_id)
+        .any(|m| m.player_id == player_id && m.role == MEMBER_ROLE_SPECTATOR)
+}
+
+/// Seats the lobby's players who are not on a team yet.
+///
+/// The first call draws the teams: players are taken in Elo order and snake-drafted (1-2-2-1...)
+/// so team ratings come out close, and each team's highest-rated member is its captain. Later
+/// calls put players who joined mid-game on the smallest team, the lower-rated one on a tie.
+fn assign_teams(ctx: &ReducerContext, lobby: &Lobby) {
+    let assigned: std::collections::HashSet<Identity> = ctx.db.team_member().lobby_id().filter(lobby.lobby_id)
+        .map(|m| m.player_id)
+        .collect();
+    let mut unassigned: Vec<Player> = ctx.db.lobby_member().lobby_id().filter(lobby.lobby_id)
+        .filter(|m| m.role == MEMBER_ROLE_PLAYER && !assigned.contains(&m.player_id))
+        .filter_map(|m| ctx.db.player().player_id().find(m.player_id))
+        .collect();
+    if unassigned.is_empty() {
+        return;
+    }
+    unassigned.sort_by_key(|p| std::cmp::Reverse(p.elo));
+
+    let mut teams: Vec<LobbyTeam> = ctx.db.lobby_team().lobby_id().filter(lobby.lobby_id).collect();
+    if teams.is_empty() {
+        for (i, members) in draft_teams(unassigned, lobby.team_count as usize).into_iter().enumerate() {
+            let Some(captain) = members.first() else {
+                continue;
+            };
+            let team = ctx.db.lobby_team().insert(LobbyTeam {
+                team_id: 0,
+                lobby_id: lobby.lobby_id,
+                team_number: i as u32 + 1,
+                captain_id: captain.player_id,
+                rating: team_rating(&members.iter().map(|p| p.elo).collect::<Vec<_>>()),
+                points: 0,
+            });
+            for member in members {
+                ctx.db.team_member().insert(TeamMember { team_member_id: 0, lobby_id: lobby.lobby_id, team_id: team.team_id, player_id: member.player_id });
+            }
+            log::info!("Lobby {} team {} drawn with captain {}", lobby.lobby_id, team.team_number, team.captain_id);
+        }
+        return;
+    }
+
+    for player in unassigned {
+        let Some(team) = teams.iter_mut()
+            .min_by_key(|t| (ctx.db.team_member().team_id().filter(t.team_id).count(), t.rating)) else {
+            return;
+        };
+        ctx.db.team_member().insert(TeamMember { team_member_id: 0, lobby_id: lobby.lobby_id, team_id: team.team_id, player_id: player.player_id });
+        log::info!("Player {} joins team {} in lobby {}", player.player_id, team.team_number, lobby.lobby_id);
+        *team = refresh_team_rating(ctx, team.clone());
+    }
+}
+
+/// Recomputes the team's rating from its members' current Elo.
+fn refresh_team_rating(ctx: &ReducerContext, mut team: LobbyTeam) -> LobbyTeam {
+    let elos: Vec<i32> = ctx.db.team_member().team_id().filter(team.team_id)
+        .filter_map(|m| ctx.db.player().player_id().find(m.player_id))
+        .map(|p| p.elo)
+        .collect();
+    team.rating = team_rating(&elos);
+    ctx.db.lobby_team().team_id().update(team)
+}
+
+/// The player's team in the lobby, if the lobby plays in teams.
+fn team_of(ctx: &ReducerContext, lobby_id: u64, player_id: Identity) -> Option<LobbyTeam> {
+    ctx.db.team_member().player_id().filter(player_id)
+        .find(|m| m.lobby_id == lobby_id)
+        .and_then(|m| ctx.db.lobby_team().team_id().find(m.team_id))
+}
+
+/// Adds each team's points for a scored round under the lobby's team scoring policy.
+fn score_team_round(ctx: &ReducerContext, lobby: &Lobby, scored_answers: &[Answer], points_for_correct: u32) {
+    let teams: Vec<LobbyTeam> = ctx.db.lobby_team().lobby_id().filter(lobby.lobby_id).collect();
+    for mut team in teams {
+        let members: std::collections::HashSet<Identity> = ctx.db.team_member().team_id().filter(team.team_id)
+            .map(|m| m.player_id)
+            .collect();
+        let mut team_answers: Vec<&Answer> = scored_answers.iter().filter(|a| members.contains(&a.player_id)).collect();
+        team_answers.sort_by_key(|a| a.submitted_at);
+        let answers: Vec<(u32, u32, bool)> = team_answers.iter()
+            .map(|a| (a.chosen_answer_index, a.score.unwrap_or(0), a.player_id == team.captain_id))
+            .collect();
+        let points = team_round_points(&lobby.team_scoring, &answers, points_for_correct);
+        log::info!("Team {} in lobby {} scores {} ({})", team.team_number, lobby.lobby_id, points, lobby.team_scoring);
+        team.points += points;
+        ctx.db.lobby_team().team_id().update(team);
+    }
+}
+
+/// Settles a team game on each team's points (see `team_member_deltas`).
+/// `team_ids` is parallel to `players`; players without a team are not rated.
+fn team_elo_deltas(ctx: &ReducerContext, players: &[(Player, u32)], team_ids: &[Option<u64>]) -> Vec<i32> {
+    let members: Vec<(i32, f32)> = players.iter().map(|(p, _)| (p.elo, k_factor_for(p.games_played, p.elo))).collect();
+    team_member_deltas(&members, team_ids, |team_id| ctx.db.lobby_team().team_id().find(team_id).map(|t| t.points).unwrap_or(0))
+}
+
+/// Settles a battle royale round (see `royale_round_outcome`) and moves the players it
+/// eliminates to the spectators.
+///
+/// Returns true once the battle royale is over.
+fn settle_royale_round(ctx: &ReducerContext, lobby: &Lobby, round: &ActiveRound, scored_answers: &[Answer]) -> bool {
+    let survivors: Vec<RoyaleStanding> = ctx.db.royale_standing().lobby_id().filter(lobby.lobby_id)
+        .filter(|s| s.placement.is_none())
+        .collect();
+    let results: Vec<(u32, bool)> = survivors.iter()
+        .map(|s| (s.lives, scored_answers.iter().any(|a| a.player_id == s.player_id && a.score.unwrap_or(0) > 0)))
+        .collect();
+    let rounds_played = ctx.db.active_round().lobby_id().filter(lobby.lobby_id).count() as u32;
+    let outcome = royale_round_outcome(&results, rounds_played, lobby.royale_round_cap);
+
+    for ((mut standing, lives), placement) in survivors.into_iter().zip(outcome.lives).zip(outcome.placements) {
+        standing.lives = lives;
+        standing.placement = placement;
+        let Some(placement) = placement.filter(|_| lives == 0) else {
+            ctx.db.royale_standing().standing_id().update(standing);
+            continue;
+        };
+        log::info!("Player {} eliminated from lobby {} in place {}", standing.player_id, lobby.lobby_id, placement);
+        log_game_event(ctx, round, GAME_EVENT_PLAYER_ELIMINATED, Some(standing.player_id), None, Some(placement as i64));
+        let memberships: Vec<LobbyMember> = ctx.db.lobby_member().lobby_id().filter(lobby.lobby_id)
+            .filter(|m| m.player_id == standing.player_id)
+            .collect();
+        for mut member in memberships {
+            member.role = MEMBER_ROLE_SPECTATOR.to_string();
+            member.joins_next_round = false;
+            ctx.db.lobby_member().member_id().update(member);
+        }
+        standing.eliminated_in_round = Some(round.round_id);
+        ctx.db.royale_standing().standing_id().update(standing);
+    }
+    outcome.over
+}
+
+/// Clears the session of every member still pointing at the lobby, once it has finished or been abandoned.
+fn clear_lobby_sessions(ctx: &ReducerContext, lobby_id: u64) {
+    for member in ctx.db.lobby_member().lobby_id().filter(lobby_id) {
+        if ctx.db.player_session().player_id().find(member.player_id).is_some_and(|s| s.lobby_id == Some(lobby_id)) {
+            set_player_session(ctx, member.player_id, None, None);
+        }
+    }
+}
+
+/// True while the lobby can still be joined, played or left (it has neither finished nor been abandoned).
+fn lobby_is_open(lobby: &Lobby) -> bool {
+    lobby.status != LOBBY_STATUS_FINISHED && lobby.status != LOBBY_STATUS_ABANDONED
+}
+
+/// The lobby's roster as host succession sees it.
+fn lobby_seats(ctx: &ReducerContext, lobby_id: u64) -> Vec<Seat<Identity>> {
+    ctx.db.lobby_member().lobby_id().filter(lobby_id)
+        .map(|m| Seat {
+            player_id: m.player_id,
+            is_player: m.role == MEMBER_ROLE_PLAYER,
+            online: ctx.db.player().player_id().find(m.player_id).is_some_and(|p| p.online),
+            joined_at_micros: m.joined_at.to_micros_since_unix_epoch(),
+        })
+        .collect()
+}
+
+/// Applies a host change worked out by `roster`.
+fn apply_host_change(ctx: &ReducerContext, mut lobby: Lobby, change: HostChange<Identity>) {
+    match change {
+        HostChange::Keep => {}
+        HostChange::HandOff(successor) => {
+            log::info!("Lobby {} host moved from {} to {}", lobby.lobby_id, lobby.host_id, successor);
+            lobby.host_id = successor;
+            ctx.db.lobby().lobby_id().update(lobby);
+        }
+        HostChange::Abandon => abandon_lobby(ctx, lobby),
+    }
+}
+
+/// Marks the lobby abandoned and clears out its roster and unplayed rounds. Nothing is rated.
+fn abandon_lobby(ctx: &ReducerContext, mut lobby: Lobby) {
+    log::info!("Lobby {} abandoned", lobby.lobby_id);
+    clear_lobby_sessions(ctx, lobby.lobby_id);
+    let members: Vec<LobbyMember> = ctx.db.lobby_member().lobby_id().filter(lobby.lobby_id).collect();
+    for member in members {
+        ctx.db.lobby_member().member_id().delete(member.member_id);
+    }
+    let rounds: Vec<ActiveRound> = ctx.db.active_round().lobby_id().filter(lobby.lobby_id).collect();
+    for round in rounds {
+        let answers: Vec<Answer> = ctx.db.answer().round_id().filter(round.round_id).collect();
+        for answer in answers {
+            ctx.db.answer().answer_id().delete(answer.answer_id);
+        }
+        ctx.db.round_result().round_id().delete(round.round_id);
+        ctx.db.active_round().round_id().delete(round.round_id);
+    }
+    ctx.db.lobby_score().lobby_player().delete(lobby.lobby_id);
+    let teams: Vec<LobbyTeam> = ctx.db.lobby_team().lobby_id().filter(lobby.lobby_id).collect();
+    for team in teams {
+        ctx.db.team_member().team_id().delete(team.team_id);
+        ctx.db.lobby_team().team_id().delete(team.team_id);
+    }
+    ctx.db.royale_standing().lobby_id().delete(lobby.lobby_id);
+    lobby.status = LOBBY_STATUS_ABANDONED.to_string();
+    ctx.db.lobby().lobby_id().update(lobby);
+}
+
+/// Takes the player off the lobby's roster, passing on the host role if they held it.
+fn remove_lobby_member(ctx: &ReducerContext, lobby_id: u64, player_id: Identity) {
+    let memberships: Vec<LobbyMember> = ctx.db.lobby_member().lobby_id().filter(lobby_id)
+        .filter(|m| m.player_id == player_id)
+        .collect();
+    for membership in memberships {
+        ctx.db.lobby_member().member_id().delete(membership.member_id);
+    }
+    if ctx.db.player_session().player_id().find(player_id).is_some_and(|s| s.lobby_id == Some(lobby_id)) {
+        set_player_session(ctx, player_id, None, None);
+    }
+    if let Some(lobby) = ctx.db.lobby().lobby_id().find(lobby_id).filter(lobby_is_open) {
+        let change = after_leaving(&lobby_seats(ctx, lobby_id), lobby.host_id, player_id, lobby.status == LOBBY_STATUS_WAITING);
+        apply_host_change(ctx, lobby, change);
+    }
+}
+
+/// Average matchmaking rating of the lobby's players (just the host if there are none).
+fn lobby_average_rating(ctx: &ReducerContext, lobby: &Lobby) -> i32 {
+    let topic = lobby.topic.as_deref();
+    let ratings: Vec<i32> = ctx.db.lobby_member().lobby_id().filter(lobby.lobby_id)
+        .filter(|m| m.role == MEMBER_ROLE_PLAYER)
+        .map(|m| active_rating(ctx, m.player_id, topic))
+        .collect();
+    if ratings.is_empty() {
+        return active_rating(ctx, lobby.host_id, topic);
+    }
+    ratings.iter().sum::<i32>() / ratings.len() as i32
+}
+
+/// Appends an event to the lobby's game log.
+fn log_game_event(
+    ctx: &ReducerContext,
+    round: &ActiveRound,
+    kind: &str,
+    player_id: Option<Identity>,
+    answer_index: Option<u32>,
+    value: Option<i64>,
+) {
+    ctx.db.game_event().insert(GameEvent {
+        event_id: 0,
+        lobby_id: round.lobby_id,
+        round_id: round.round_id,
+        kind: kind.to_string(),
+        player_id,
+        answer_index,
+        value,
+        occurred_at: ctx.timestamp,
+    });
+}
+
+/// Logs the opening of a freshly created round, and its lightning status.
+fn log_round_opened(ctx: &ReducerContext, round: &ActiveRound) {
+    log_game_event(ctx, round, GAME_EVENT_ROUND_OPENED, None, None, Some(round.question_id as i64));
+    if round.is_lightning {
+        log_game_event(ctx, round, GAME_EVENT_LIGHTNING_TRIGGERED, None, None, None);
+    }
+}
+
+/// Looks for highlight moments in a round that has just been scored.
+fn find_round_highlights(ctx: &ReducerContext, round: &ActiveRound, scored_at: Timestamp) -> Vec<Highlight> {
+    let mut highlights = Vec::new();
+    let highlight = |kind: &str, player_id: Option<Identity>, started_at: Timestamp, ended_at: Timestamp| Highlight {
+        highlight_id: 0,
+        lobby_id: round.lobby_id,
+        round_id: round.round_id,
+        kind: kind.to_string(),
+        player_id,
+        started_at,
+        ended_at,
+        detected_at: ctx.timestamp,
+    };
+
+    let answers: Vec<Answer> = ctx.db.answer().round_id().filter(round.round_id).collect();
+    if answers.is_empty() {
+        return highlights;
+    }
+    let round_answers: Vec<RoundAnswer<Identity>> = answers.iter()
+        .map(|a| RoundAnswer {
+            player_id: a.player_id,
+            elo: ctx.db.player().player_id().find(a.player_id).map(|p| p.elo).unwrap_or(INITIAL_ELO),
+            answer_index: a.chosen_answer_index,
+            points: a.score.unwrap_or(0),
+            submitted_at_micros: a.submitted_at.to_micros_since_unix_epoch(),
+        })
+        .collect();
+    let at = Timestamp::from_micros_since_unix_epoch;
+
+    if let Some(player_id) = upset(&round_answers) {
+        highlights.push(highlight(HIGHLIGHT_UPSET, Some(player_id), round.start_time, scored_at));
+    }
+
+    if let Some((player_id, first, second)) = photo_finish(&round_answers) {
+        highlights.push(highlight(HIGHLIGHT_PHOTO_FINISH, Some(player_id), at(first), at(second)));
+    }
+
+    if round.is_lightning {
+        let standings: Vec<(Identity, u32, u32)> = ctx.db.lobby_score().lobby_player().filter(round.lobby_id)
+            .filter(|entry| !is_spectator(ctx, round.lobby_id, entry.player_id))
+            .map(|entry| {
+                let round_points = answers.iter().find(|a| a.player_id == entry.player_id).and_then(|a| a.score).unwrap_or(0);
+                (entry.player_id, entry.points.saturating_sub(round_points), entry.points)
+            })
+            .collect();
+        for player_id in lightning_comebacks(&standings) {
+            highlights.push(highlight(HIGHLIGHT_LIGHTNING_COMEBACK, Some(player_id), round.start_time, scored_at));
+        }
+    }
+
+    if let Some((started, ended)) = crowd_stampede(&round_answers) {
+        highlights.push(highlight(HIGHLIGHT_CROWD_STAMPEDE, None, at(started), at(ended)));
+    }
+
+    highlights
+}
+
+/// Current count per answer index for the round, from the private stats.
+fn crowd_meter_counts(ctx: &ReducerContext, round_id: u64) -> Vec<u32> {
+    counts_by_index(ctx.db.crowd_meter_stats().round_answer().filter(round_id).map(|s| (s.answer_index, s.count)))
+}
+
+/// The crowd_meter_stats table as `record_answer` sees it.
+struct CrowdMeterStatsTable<'a>(&'a ReducerContext);
+
+impl CrowdMeterStore for CrowdMeterStatsTable<'_> {
+    fn find(&self, round_id: u64, answer_index: u32) -> Option<(u64, u32)> {
+        self.0.db.crowd_meter_stats().round_answer().filter((round_id, answer_index)).next().map(|s| (s.stat_id, s.count))
+    }
+
+    fn update(&mut self, stat_id: u64, round_id: u64, answer_index: u32, count: u32) {
+        self.0.db.crowd_meter_stats().stat_id().update(CrowdMeterStats { stat_id, round_id, answer_index, count });
+    }
+
+    fn insert(&mut self, round_id: u64, answer_index: u32, count: u32) {
+        self.0.db.crowd_meter_stats().insert(CrowdMeterStats { stat_id: 0, round_id, answer_index, count });
+    }
+}
+
+/// Writes `counts` to the round's public crowd meter, logging a game event for every choice that changed.
+/// In fog mode only percentages are written.
+fn publish_crowd_meter(ctx: &ReducerContext, round: &ActiveRound, counts: &[u32], fog: bool) {
+    for view in public_views(counts, fog) {
+        let existing = ctx.db.crowd_meter_view().round_answer().filter((round.round_id, view.answer_index)).next();
+        let previous = existing.as_ref().map(|v| MeterView { answer_index: v.answer_index, count: v.count, percent: v.percent });
+        if previous.as_ref() == Some(&view) {
+            continue;
+        }
+        if let Some(event_value) = change_event(previous.as_ref(), &view) {
+            log_game_event(ctx, round, GAME_EVENT_CROWD_METER_CHANGED, None, Some(view.answer_index), Some(event_value));
+        }
+        let row = CrowdMeterView {
+            view_id: existing.as_ref().map(|v| v.view_id).unwrap_or(0),
+            round_id: round.round_id,
+            answer_index: view.answer_index,
+            count: view.count,
+            percent: view.percent,
+            updated_at: ctx.timestamp,
+        };
+        if existing.is_some() {
+            ctx.db.crowd_meter_view().view_id().update(row);
+        } else {
+            ctx.db.crowd_meter_view().insert(row);
+        }
+    }
+}
+
+/// Publishes the crowd meter after `viewer_id` answered, as the lobby's visibility mode allows.
+fn publish_crowd_meter_for_mode(ctx: &ReducerContext, lobby: &Lobby, round: &ActiveRound, viewer_id: Identity) {
+    let counts = crowd_meter_counts(ctx, round.round_id);
+    match on_answer(&lobby.crowd_meter_mode, lobby.crowd_meter_delay_secs) {
+        OnAnswer::Publish { fog } => publish_crowd_meter(ctx, round, &counts, fog),
+        OnAnswer::ReleaseAfterMicros(delay_micros) => {
+            ctx.db.crowd_meter_release().insert(CrowdMeterRelease {
+                scheduled_id: 0,
+                scheduled_at: (ctx.timestamp + TimeDuration::from_micros(delay_micros)).into(),
+                round_id: round.round_id,
+                counts,
+            });
+        }
+        OnAnswer::SnapshotForAnswerer => {
+            let total: u32 = counts.iter().sum();
+            for (index, &count) in counts.iter().enumerate() {
+                ctx.db.crowd_meter_answered_view().insert(CrowdMeterAnsweredView {
+                    view_id: 0,
+                    round_id: round.round_id,
+                    viewer_id,
+                    answer_index: index as u32,
+                    count,
+                    percent: percent_of(count, total),
+                    snapshot_at: ctx.timestamp,
+                });
+            }
+        }
+        OnAnswer::Hide => {}
+    }
+}
+
+/// Once a round is scored, everyone sees the final counts and the per-player snapshots are dropped.
+fn reveal_crowd_meter(ctx: &ReducerContext, round: &ActiveRound) {
+    let counts = crowd_meter_counts(ctx, round.round_id);
+    publish_crowd_meter(ctx, round, &counts, false);
+    let snapshots: Vec<u64> = ctx.db.crowd_meter_answered_view().round_id().filter(round.round_id).map(|v| v.view_id).collect();
+    for view_id in snapshots {
+        ctx.db.crowd_meter_answered_view().view_id().delete(view_id);
+    }
+}
+
+/// Adds the round's points to the lobby scoreboard and re-ranks it. Players appear on the
+/// scoreboard from their first answer, even a wrong one. Streaks that reach a multiple of
+/// COMBO_STREAK are logged as combos.
+fn add_lobby_scores(ctx: &ReducerContext, round: &ActiveRound, round_points: &[(Identity, u32)]) {
+    let lobby_id = round.lobby_id;
+    // Anyone already on the scoreboard who sat this round out loses their streak
+    let missed: Vec<LobbyScore> = ctx.db.lobby_score().lobby_player().filter(lobby_id)
+        .filter(|e| e.streak > 0 && !round_points.iter().any(|(player_id, _)| *player_id == e.player_id))
+        .collect();
+    for mut entry in missed {
+        entry.streak = 0;
+        ctx.db.lobby_score().score_id().update(entry);
+    }
+
+    for (player_id, points) in round_points {
+        let entry = ctx.db.lobby_score().lobby_player().filter((lobby_id, *player_id)).next();
+        let line = entry.as_ref()
+            .map(|e| ScoreLine { points: e.points, correct_count: e.correct_count, streak: e.streak })
+            .unwrap_or_default();
+        let (line, combo) = add_round(line, *points);
+        if combo {
+            log::info!("Player {} is on a {}-round streak in lobby {}", player_id, line.streak, lobby_id);
+            log_game_event(ctx, round, GAME_EVENT_COMBO_AWARDED, Some(*player_id), None, Some(line.streak as i64));
+        }
+        match entry {
+            Some(mut entry) => {
+                entry.points = line.points;
+                entry.correct_count = line.correct_count;
+                entry.streak = line.streak;
+                entry.updated_at = ctx.timestamp;
+                ctx.db.lobby_score().score_id().update(entry);
+            }
+            None => {
+                let player_name = ctx.db.player().player_id().find(*player_id).map(|p| p.name).unwrap_or_default();
+                ctx.db.lobby_score().insert(LobbyScore {
+                    score_id: 0,
+                    lobby_id,
+                    player_id: *player_id,
+                    player_name,
+                    points: line.points,
+                    correct_count: line.correct_count,
+                    streak: line.streak,
+                    rank: 0,
+                    updated_at: ctx.timestamp,
+                });
+            }
+        }
+    }
+
+    rerank_lobby_scores(ctx, lobby_id);
+}
+
+/// Re-ranks the lobby scoreboard by points.
+fn rerank_lobby_scores(ctx: &ReducerContext, lobby_id: u64) {
+    let mut entries: Vec<LobbyScore> = ctx.db.lobby_score().lobby_player().filter(lobby_id).collect();
+    entries.sort_by_key(|e| std::cmp::Reverse(e.points));
+    let ranks = competition_ranks(&entries.iter().map(|e| e.points).collect::<Vec<_>>());
+    for (mut entry, rank) in entries.into_iter().zip(ranks) {
+        if entry.rank != rank {
+            entry.rank = rank;
+            ctx.db.lobby_score().score_id().update(entry);
+        }
+    }
+}
+
+/// Points for a correct answer in the round; lightning rounds are worth double.
+fn points_for_correct(round: &ActiveRound) -> u32 {
+    if round.is_lightning { 20 } else { 10 }
+}
+
+/// Closes a round whose answers have all been scored: publishes its result and the lobby's
+/// running totals, reveals the crowd meter and queues the round for highlight detection.
+fn finish_scored_round(ctx: &ReducerContext, round: &ActiveRound, question: &Question, scored_answers: &[Answer]) {
+    let answers: Vec<(Identity, u32, i64)> = scored_answers.iter()
+        .map(|a| (a.player_id, a.score.unwrap_or(0), a.submitted_at.to_micros_since_unix_epoch()))
+        .collect();
+    let correct_answers = correct_by_speed(&answers);
+    let choice_counts = padded_counts(crowd_meter_counts(ctx, round.round_id), question.wrong_answers.len() + 1);
+    ctx.db.round_result().insert(RoundResult {
+        round_id: round.round_id,
+        lobby_id: round.lobby_id,
+        question_id: round.question_id,
+        correct_answer_index: 0,
+        choice_counts,
+        correct_players: correct_answers.iter().map(|&(player_id, _)| player_id).collect(),
+        points_awarded: correct_answers.iter().map(|&(_, points)| points).collect(),
+        scored_at: ctx.timestamp,
+    });
+    let round_points: Vec<(Identity, u32)> = scored_answers.iter().map(|a| (a.player_id, a.score.unwrap_or(0))).collect();
+    add_lobby_scores(ctx, round, &round_points);
+
+    let mut finished_round = round.clone();
+    finished_round.status = ROUND_STATUS_FINISHED.to_string();
+    ctx.db.active_round().round_id().update(finished_round);
+    reveal_crowd_meter(ctx, round);
+    log_game_event(ctx, round, GAME_EVENT_ROUND_SCORED, None, None, Some(correct_answers.len() as i64));
+    ctx.db.pending_highlight_scan().insert(PendingHighlightScan { round_id: round.round_id, scored_at: ctx.timestamp });
+}
+
+/// Buzzer mode: judges a buzz as soon as it arrives instead of waiting for score_round.
+///
+/// Reducers run one at a time in the order they reach the server, so the first buzz holds the
+/// round while it is judged and later buzzes queue behind it. A correct buzz takes the points
+/// and ends the round. A wrong buzz locks its player out (they already have an answer for the
+/// round) and the round stays open for everyone else, until no player is left to buzz.
+fn judge_buzz(ctx: &ReducerContext, lobby: &Lobby, round: &ActiveRound, mut buzz: Answer) {
+    let buzzed: Vec<Identity> = ctx.db.answer().round_id().filter(round.round_id).map(|b| b.player_id).collect();
+    let players_left = ctx.db.lobby_member().lobby_id().filter(lobby.lobby_id)
+        .filter(|m| m.role == MEMBER_ROLE_PLAYER && !buzzed.contains(&m.player_id))
+        .count();
+    let outcome = buzz_outcome(buzz.chosen_answer_index, points_for_correct(round), players_left);
+    let score = outcome.points;
+    buzz.score = Some(score);
+    let buzz = ctx.db.answer().answer_id().update(buzz);
+    log_game_event(ctx, round, GAME_EVENT_ANSWER_SCORED, Some(buzz.player_id), Some(buzz.chosen_answer_index), Some(score as i64));
+    if score > 0 {
+        if let Some(mut player) = ctx.db.player().player_id().find(buzz.player_id) {
+            player.score += score;
+            player.season_score += score;
+            ctx.db.player().player_id().update(player);
+        }
+    }
+    if !outcome.closes_round {
+        log::info!("Player {} buzzed wrong in round {} and is locked out", buzz.player_id, round.round_id);
+        return;
+    }
+
+    log::info!("Buzzer round {} closed by player {} ({} points)", round.round_id, buzz.player_id, score);
+    // Buzzes are a race, not a fair sample of who knows the answer, so they do not calibrate the question
+    if let Some(question) = ctx.db.question_bank().question_id().find(round.question_id) {
+        let buzzes: Vec<Answer> = ctx.db.answer().round_id().filter(round.round_id).collect();
+        finish_scored_round(ctx, round, &question, &buzzes);
+    }
+}
+
+/// Picks the question for the lobby's next round according to its question mode.
+///
+/// Topic lobbies only draw from their topic, and questions already played in the lobby are
+/// skipped while unplayed ones remain.
+fn select_question(ctx: &ReducerContext, lobby: &Lobby) -> Result<Question, String> {
+    let played: Vec<u64> = ctx.db.active_round().lobby_id().filter(lobby.lobby_id).map(|r| r.question_id).collect();
+
+    let mut candidates: Vec<Question> = match &lobby.topic {
+        Some(topic) => ctx.db.question_bank().topic().filter(topic).collect(),
+        None => ctx.db.question_bank().iter().collect(),
+    };
+    if candidates.is_empty() {
+        return Err(format!("No questions available for topic {}", lobby.topic.as_deref().unwrap_or_default()));
+    }
+    skip_played(&mut candidates, &played);
+
+    let chosen: Vec<Question> = match lobby.question_mode.as_str() {
+        QUESTION_MODE_ADAPTIVE => {
+            let average_rating = lobby_average_rating(ctx, lobby);
+            let (low, high) = item_rating_range_for_success(average_rating, ADAPTIVE_MIN_SUCCESS, ADAPTIVE_MAX_SUCCESS);
+            adaptive_choices(candidates, low, high)
+        }
+        QUESTION_MODE_FIXED => {
+            let difficulty = lobby.fixed_difficulty.as_deref().unwrap_or(DIFFICULTY_MEDIUM);
+            let fixed = fixed_choices(candidates, difficulty);
+            if fixed.is_empty() {
+                return Err(format!("No questions available at difficulty {}", difficulty));
+            }
+            fixed
+        }
+        _ => mixed_choices(candidates, played.len()),
+    };
+
+    // Use timestamp value directly for randomization
+    let random_index = (ctx.timestamp.to_micros_since_unix_epoch() % chosen.len() as i64) as usize;
+    chosen.into_iter().nth(random_index)
+        .ok_or_else(|| "Failed to select random question".to_string())
+}
+
+/// Board name for the weekly points leaderboard containing `timestamp`.
+fn weekly_leaderboard(timestamp: Timestamp) -> String {
+    let micros = timestamp.to_micros_since_unix_epoch();
+    let week_start = week_start_micros(micros);
+    let monday = Timestamp::from_micros_since_unix_epoch(week_start).to_rfc3339()
+        .map(|date| date[..10].to_string())
+        .unwrap_or_else(|_| week_start.to_string());
+    format!("{}{}", LEADERBOARD_WEEKLY_PREFIX, monday)
+}
+
+/// One board of the leaderboard table, seen through `LeaderboardStore`. Entries it writes carry `player_name`.
+struct LeaderboardTable<'a> {
+    ctx: &'a ReducerContext,
+    board: &'a str,
+    player_name: &'a str,
+}
+
+impl LeaderboardStore for LeaderboardTable<'_> {
+    type PlayerId = Identity;
+
+    fn entries_with_values(&self, values: (std::ops::Bound<i64>, std::ops::Bound<i64>)) -> Vec<BoardEntry<Identity>> {
+        self.ctx.db.leaderboard_entry().board_value().filter((self.board, values)).map(board_entry).collect()
+    }
+
+    fn lowest_above(&self, value: i64) -> Option<BoardEntry<Identity>> {
+        self.ctx.db.leaderboard_entry().board_value().filter((self.board, (std::ops::Bound::Excluded(value), std::ops::Bound::Unbounded))).next().map(board_entry)
+    }
+
+    fn count_at(&self, value: i64) -> u32 {
+        self.ctx.db.leaderboard_entry().board_value().filter((self.board, value)).count() as u32
+    }
+
+    fn find_player(&self, player_id: Identity) -> Option<BoardEntry<Identity>> {
+        self.ctx.db.leaderboard_entry().board_player().filter((self.board, player_id)).next().map(board_entry)
+    }
+
+    fn set_rank(&mut self, entry_id: u64, rank: u32) {
+        if let Some(mut entry) = self.ctx.db.leaderboard_entry().entry_id().find(entry_id) {
+            entry.rank = rank;
+            self.ctx.db.leaderboard_entry().entry_id().update(entry);
+        }
+    }
+
+    fn set_value(&mut self, entry_id: u64, value: i64) {
+        if let Some(mut entry) = self.ctx.db.leaderboard_entry().entry_id().find(entry_id) {
+            entry.value = value;
+            entry.player_name = self.player_name.to_string();
+            entry.updated_at = self.ctx.timestamp;
+            self.ctx.db.leaderboard_entry().entry_id().update(entry);
+        }
+    }
+
+    fn insert(&mut self, player_id: Identity, value: i64, rank: u32) {
+        self.ctx.db.leaderboard_entry().insert(LeaderboardEntry {
+            entry_id: 0,
+            board: self.board.to_string(),
+            player_id,
+            player_name: self.player_name.to_string(),
+            value,
+            rank,
+            updated_at: self.ctx.timestamp,
+        });
+    }
+
+    fn delete(&mut self, entry_id: u64) {
+        self.ctx.db.leaderboard_entry().entry_id().delete(entry_id);
+    }
+}
+
+fn board_entry(entry: LeaderboardEntry) -> BoardEntry<Identity> {
+    BoardEntry { entry_id: entry.entry_id, player_id: entry.player_id, value: entry.value, rank: entry.rank }
+}
+
+/// Inserts or moves a player's entry on `board`, shifting only the ranks between the old and new value.
+fn upsert_leaderboard_entry(ctx: &ReducerContext, board: &str, player_id: Identity, player_name: &str, value: i64) {
+    upsert_entry(&mut LeaderboardTable { ctx, board, player_name }, player_id, value);
+}
+
+/// Removes a player's entry from `board`, moving everyone below them up one rank.
+fn remove_leaderboard_entry(ctx: &ReducerContext, board: &str, player_id: Identity) {
+    remove_entry(&mut LeaderboardTable { ctx, board, player_name: "" }, player_id);
+}
+
+/// Puts the player on the all-time rating board once their rating is no longer provisional.
+fn refresh_rating_leaderboard(ctx: &ReducerContext, player: &Player) {
+    if player.provisional {
+        remove_leaderboard_entry(ctx, LEADERBOARD_RATING, player.player_id);
+    } else {
+        let rating = active_rating(ctx, player.player_id, None);
+        upsert_leaderboard_entry(ctx, LEADERBOARD_RATING, player.player_id, &player.name, rating as i64);
+    }
+}
+
+/// Rebuilds the all-time rating board from scratch, for changes that move every rating at once.
+fn rebuild_rating_leaderboard(ctx: &ReducerContext) {
+    let entries: Vec<(Identity, String, i64)> = ctx.db.player().iter()
+        .filter(|p| !p.provisional)
+        .map(|p| (p.player_id, p.name.clone(), active_rating(ctx, p.player_id, None) as i64))
+        .collect();
+    refill_leaderboard(ctx, LEADERBOARD_RATING, entries);
+}
+
+/// Rebuilds a topic's rating board from scratch, for changes that move every topic rating at once.
+fn rebuild_topic_leaderboard(ctx: &ReducerContext, topic: &str) {
+    let entries: Vec<(Identity, String, i64)> = ctx.db.topic_rating().topic().filter(topic)
+        .filter(|r| !is_provisional(r.games_played))
+        .map(|r| {
+            let name = ctx.db.player().player_id().find(r.player_id).map(|p| p.name).unwrap_or_default();
+            (r.player_id, name, r.elo as i64)
+        })
+        .collect();
+    refill_leaderboard(ctx, &format!("{}{}", LEADERBOARD_TOPIC_PREFIX, topic), entries);
+}
+
+/// Replaces everything on `board` with `(player, name, value)` entries ranked in one pass.
+fn refill_leaderboard(ctx: &ReducerContext, board: &str, mut entries: Vec<(Identity, String, i64)>) {
+    ctx.db.leaderboard_entry().board_rank().delete(board);
+    entries.sort_by_key(|e| std::cmp::Reverse(e.2));
+
+    let ranks = competition_ranks(&entries.iter().map(|e| e.2).collect::<Vec<_>>());
+    for ((player_id, player_name, value), rank) in entries.into_iter().zip(ranks) {
+        ctx.db.leaderboard_entry().insert(LeaderboardEntry {
+            entry_id: 0,
+            board: board.to_string(),
+            player_id,
+            player_name,
+            value,
+            rank,
+            updated_at: ctx.timestamp,
+        });
+    }
+}
+
+/// True when the caller connected without signing in: no token, or one minted by a guest token issuer.
+fn is_guest(ctx: &ReducerContext) -> bool {
+    ctx.sender_auth().jwt().is_none_or(|claims| ctx.db.guest_token_issuer().issuer().find(claims.issuer().to_string()).is_some())
+}
+
+/// True while the player is in a lobby that has not finished or been abandoned.
+fn in_unfinished_lobby(ctx: &ReducerContext, player_id: Identity) -> bool {
+    ctx.db.lobby_member().player_id().filter(player_id).any(|m| {
+        ctx.db.lobby().lobby_id().find(m.lobby_id).is_some_and(|l| lobby_is_open(&l))
+    })
+}
+
+/// Moves everything the guest has earned onto the account and deletes the guest player.
+/// The more experienced of the two ratings is kept; points and game counts are added up.
+fn merge_guest_into_account(ctx: &ReducerContext, guest: Player, account: Option<Player>) {
+    let account_id = ctx.sender;
+    let guest_glicko = ctx.db.player_glicko().player_id().find(guest.player_id);
+    let account_glicko = ctx.db.player_glicko().player_id().find(account_id);
+    let guest_is_stronger_record = account.as_ref().is_none_or(|a| guest.games_played > a.games_played);
+
+    ctx.db.player().player_id().delete(guest.player_id);
+    ctx.db.player_glicko().player_id().delete(guest.player_id);
+
+    let merged = match account {
+        Some(account) => {
+            let games_played = account.games_played + guest.games_played;
+            let merged = Player {
+                score: account.score + guest.score,
+                season_score: account.season_score + guest.season_score,
+                elo: if guest_is_stronger_record { guest.elo } else { account.elo },
+                games_played,
+                provisional: is_provisional(games_played),
+                ..account
+            };
+            ctx.db.player().player_id().update(merged.clone());
+            merged
+        }
+        None => {
+            let merged = Player { player_id: account_id, ..guest.clone() };
+            ctx.db.player().insert(merged.clone());
+            merged
+        }
+    };
+
+    let glicko = match (guest_glicko, account_glicko) {
+        (Some(g), Some(_)) if guest_is_stronger_record => PlayerGlicko { player_id: account_id, ..g },
+        (_, Some(a)) => a,
+        (Some(g), None) => PlayerGlicko { player_id: account_id, ..g },
+        (None, None) => PlayerGlicko::new(account_id),
+    };
+    if ctx.db.player_glicko().player_id().find(account_id).is_some() {
+        ctx.db.player_glicko().player_id().update(glicko);
+    } else {
+        ctx.db.player_glicko().insert(glicko);
+    }
+
+    let guest_topics: Vec<TopicRating> = ctx.db.topic_rating().player_topic().filter(guest.player_id).collect();
+    for guest_topic in guest_topics {
+        let topic_board = format!("{}{}", LEADERBOARD_TOPIC_PREFIX, guest_topic.topic);
+        remove_leaderboard_entry(ctx, &topic_board, guest.player_id);
+        let account_topic = ctx.db.topic_rating().player_topic().filter((account_id, guest_topic.topic.as_str())).next();
+        let merged_topic = match account_topic {
+            Some(existing) => {
+                ctx.db.topic_rating().topic_rating_id().delete(guest_topic.topic_rating_id);
+                TopicRating {
+                    elo: if guest_topic.games_played > existing.games_played { guest_topic.elo } else { existing.elo },
+                    games_played: existing.games_played + guest_topic.games_played,
+                    ..existing
+                }
+            }
+            None => TopicRating { player_id: account_id, ..guest_topic },
+        };
+        if !is_provisional(merged_topic.games_played) {
+            upsert_leaderboard_entry(ctx, &topic_board, account_id, &merged.name, merged_topic.elo as i64);
+        }
+        ctx.db.topic_rating().topic_rating_id().update(merged_topic);
+    }
+
+    move_guest_rows(ctx, guest.player_id, &merged);
+
+    let history: Vec<RatingHistory> = ctx.db.rating_history().player_id().filter(guest.player_id).collect();
+    for entry in history {
+        ctx.db.rating_history().history_id().update(RatingHistory { player_id: account_id, ..entry });
+    }
+
+    // Weekly points add up; the rating board is refreshed from the merged rating below
+    let guest_entries: Vec<LeaderboardEntry> = ctx.db.leaderboard_entry().player_id().filter(guest.player_id).collect();
+    for entry in guest_entries {
+        remove_leaderboard_entry(ctx, &entry.board, guest.player_id);
+        if entry.board.starts_with(LEADERBOARD_WEEKLY_PREFIX) {
+            let account_points = ctx.db.leaderboard_entry().board_player().filter((entry.board.as_str(), account_id)).next()
+                .map(|e| e.value)
+                .unwrap_or(0);
+            upsert_leaderboard_entry(ctx, &entry.board, account_id, &merged.name, account_points + entry.value);
+        }
+    }
+    refresh_rating_leaderboard(ctx, &merged);
+}
+
+/// Moves the guest's seats, session, scoreboard lines, season results and sanctions in force onto
+/// the merged account. Rows the account already has for the same lobby or season are combined.
+fn move_guest_rows(ctx: &ReducerContext, guest_id: Identity, merged: &Player) {
+    let account_id = merged.player_id;
+    let now = ctx.timestamp.to_micros_since_unix_epoch();
+
+    let account_lobbies: Vec<u64> = ctx.db.lobby_member().player_id().filter(account_id).map(|m| m.lobby_id).collect();
+    let guest_seats: Vec<LobbyMember> = ctx.db.lobby_member().player_id().filter(guest_id).collect();
+    let taken_over = seats_to_take_over(&guest_seats.iter().map(|m| m.lobby_id).collect::<Vec<_>>(), &account_lobbies);
+    for seat in guest_seats {
+        if taken_over.contains(&seat.lobby_id) {
+            ctx.db.lobby_member().member_id().update(LobbyMember { player_id: account_id, ..seat });
+        } else {
+            ctx.db.lobby_member().member_id().delete(seat.member_id);
+        }
+    }
+
+    // Neither player is in an open lobby, so the account keeps its own session if it has one
+    if let Some(session) = ctx.db.player_session().player_id().find(guest_id) {
+        ctx.db.player_session().player_id().delete(guest_id);
+        if ctx.db.player_session().player_id().find(account_id).is_none() {
+            ctx.db.player_session().insert(PlayerSession { player_id: account_id, ..session });
+        }
+    }
+
+    // The scoreboard has no player index, but a merge is rare enough to scan it
+    let guest_scores: Vec<LobbyScore> = ctx.db.lobby_score().iter().filter(|e| e.player_id == guest_id).collect();
+    for guest_score in guest_scores {
+        let lobby_id = guest_score.lobby_id;
+        match ctx.db.lobby_score().lobby_player().filter((lobby_id, account_id)).next() {
+            Some(account_score) => {
+                ctx.db.lobby_score().score_id().delete(guest_score.score_id);
+                let line = merge_lines(
+                    ScoreLine { points: account_score.points, correct_count: account_score.correct_count, streak: account_score.streak },
+                    ScoreLine { points: guest_score.points, correct_count: guest_score.correct_count, streak: guest_score.streak },
+                );
+                ctx.db.lobby_score().score_id().update(LobbyScore {
+                    points: line.points,
+                    correct_count: line.correct_count,
+                    streak: line.streak,
+                    ..account_score
+                });
+            }
+            None => {
+                ctx.db.lobby_score().score_id().update(LobbyScore { player_id: account_id, player_name: merged.name.clone(), ..guest_score });
+            }
+        }
+        rerank_lobby_scores(ctx, lobby_id);
+    }
+
+    let guest_standings: Vec<SeasonStanding> = ctx.db.season_standing().player_id().filter(guest_id).collect();
+    for guest_standing in guest_standings {
+        let season_id = guest_standing.season_id;
+        let account_standing = ctx.db.season_standing().player_id().filter(account_id).find(|s| s.season_id == season_id);
+        match account_standing {
+            Some(account_standing) => {
+                ctx.db.season_standing().standing_id().delete(guest_standing.standing_id);
+                let line = merge_season_lines(
+                    SeasonLine { final_elo: account_standing.final_elo, games_played: account_standing.games_played, score: account_standing.score },
+                    SeasonLine { final_elo: guest_standing.final_elo, games_played: guest_standing.games_played, score: guest_standing.score },
+                );
+                ctx.db.season_standing().standing_id().update(SeasonStanding {
+                    final_elo: line.final_elo,
+                    games_played: line.games_played,
+                    provisional: is_provisional(line.games_played),
+                    score: line.score,
+                    ..account_standing
+                });
+            }
+            None => {
+                ctx.db.season_standing().standing_id().update(SeasonStanding { player_id: account_id, player_name: merged.name.clone(), ..guest_standing });
+            }
+        }
+        rerank_season_standings(ctx, season_id);
+    }
+
+    if let Some(guest_ban) = ctx.db.player_ban().player_id().find(guest_id) {
+        ctx.db.player_ban().player_id().delete(guest_id);
+        let account_ban = ctx.db.player_ban().player_id().find(account_id);
+        let account_expiry = account_ban.as_ref().map(|ban| ban.expires_at.map(|t| t.to_micros_since_unix_epoch()));
+        if guest_ban_prevails(account_expiry, guest_ban.expires_at.map(|t| t.to_micros_since_unix_epoch()), now) {
+            log::info!("Guest {}'s ban carries over to account {}", guest_id, account_id);
+            let ban = PlayerBan { player_id: account_id, ..guest_ban };
+            if account_ban.is_some() {
+                ctx.db.player_ban().player_id().update(ban);
+            } else {
+                ctx.db.player_ban().insert(ban);
+            }
+        }
+    }
+
+    let guest_mutes: Vec<PlayerMute> = ctx.db.player_mute().player_id().filter(guest_id).collect();
+    let carried = mutes_in_force(&guest_mutes.iter().map(|m| (m.mute_id, m.expires_at.map(|t| t.to_micros_since_unix_epoch()))).collect::<Vec<_>>(), now);
+    for mute in guest_mutes {
+        if carried.contains(&mute.mute_id) {
+            ctx.db.player_mute().mute_id().update(PlayerMute { player_id: account_id, ..mute });
+        } else {
+            ctx.db.player_mute().mute_id().delete(mute.mute_id);
+        }
+    }
+}
+
+/// Re-ranks a closed season's standings by final Elo.
+fn rerank_season_standings(ctx: &ReducerContext, season_id: u64) {
+    let mut standings: Vec<SeasonStanding> = ctx.db.season_standing().season_id().filter(season_id).collect();
+    standings.sort_by_key(|s| std::cmp::Reverse(s.final_elo));
+    let ranks = competition_ranks(&standings.iter().map(|s| s.final_elo).collect::<Vec<_>>());
+    for (mut standing, rank) in standings.into_iter().zip(ranks) {
+        if standing.rank != rank {
+            standing.rank = rank;
+            ctx.db.season_standing().standing_id().update(standing);
+        }
+    }
+}
+
+/// Admins moderate implicitly; everyone else needs a moderator row.
+fn is_moderator(ctx: &ReducerContext, player_id: Identity) -> bool {
+    ctx.db.admin().admin_id().find(player_id).is_some() || ctx.db.moderator().moderator_id().find(player_id).is_some()
 }
 
-#[table(name = agent_job_queue, public)]
-#[derive(Clone, Debug)] // Reverted: Removed Table
-pub struct AgentJobQueue {
-    #[primary_key]
-    #[auto_inc]
-    job_id: u64,            // Corresponds to id PK(auto_inc) in SPECS
-    agent_id: u64,          // ID of the agent to perform the work
-    payload_json: String,   // The actual work payload, e.g., topic info for question generation
-    #[index(btree)]
-    status: String,         // e.g., "pending", "processing", "completed", "failed"
-    error_message: Option<String>, // Optional: To store error details if the job failed
-    // created_at: Timestamp, // Optional: consider adding for tracking
-    // updated_at: Timestamp, // Optional: consider adding for tracking
+/// True while a sanction expiring at `expires_at` is in force.
+fn sanction_in_force(ctx: &ReducerContext, expires_at: Option<Timestamp>) -> bool {
+    is_in_force(expires_at.map(|t| t.to_micros_since_unix_epoch()), ctx.timestamp.to_micros_since_unix_epoch())
 }
 
-#[table(name = agent_registry, public)]
-#[derive(Clone, Debug)] // Reverted: Removed Table
-pub struct AgentRegistry {
-    #[primary_key]
-    #[auto_inc]
-    agent_id: u64,          // Corresponds to id PK in SPECS
-    owner_id: Identity,       // The Identity of the user who registered the agent
-    wasm_hash: String,        // SHA256 hash of the agent's WASM binary
-    capabilities: Vec<String>,// List of agent capabilities (e.g., "generate_questions")
-    energy_quota: u64,        // Resource limit for the agent
-    // registered_at: Timestamp, // Optional: for tracking registration time
-    // name: Option<String>,     // Optional: a human-readable name for the agent
-    // description: Option<String>, // Optional: a short description
+/// The player's ban, if one is currently in force.
+fn active_ban(ctx: &ReducerContext, player_id: Identity) -> Option<PlayerBan> {
+    ctx.db.player_ban().player_id().find(player_id)
+        .filter(|ban| sanction_in_force(ctx, ban.expires_at))
 }
 
-#[table(name = crowd_meter_stats, public)]
-#[derive(Clone, Debug)] // Reverted: Removed Table
-pub struct CrowdMeterStats {
-    #[primary_key] // Part 1 of composite key
-    round_id: u64,
-    #[primary_key] // Reverted to primary_key for composite key definition
-    answer_index: u32, // The index of the answer chosen (e.g., 0, 1, 2, 3)
-    count: u32,        // Number of players who chose this answer_index for this round
+/// True if the player may not speak in the lobby, either through a lobby mute or a global one.
+fn is_muted(ctx: &ReducerContext, player_id: Identity, lobby_id: u64) -> bool {
+    ctx.db.player_mute().player_id().filter(player_id)
+        .any(|mute| mute_covers(mute.lobby_id, lobby_id) && sanction_in_force(ctx, mute.expires_at))
+}
+
+/// Checks that the sender may act on `target_id`: moderators anywhere, or the host inside their own lobby.
+/// Moderators can only be acted on by admins.
+fn authorize_moderation(ctx: &ReducerContext, target_id: Identity, lobby_id: Option<u64>) -> Result<(), String> {
+    authorize(&ModerationRequest {
+        is_self: target_id == ctx.sender,
+        actor_is_admin: ctx.db.admin().admin_id().find(ctx.sender).is_some(),
+        actor_is_moderator: is_moderator(ctx, ctx.sender),
+        target_is_moderator: is_moderator(ctx, target_id),
+        actor_hosts_lobby: lobby_id.and_then(|id| ctx.db.lobby().lobby_id().find(id)).map(|lobby| lobby.host_id == ctx.sender),
+    })
+}
+
+/// Appends a row to the moderation audit log.
+fn record_moderation(ctx: &ReducerContext, target_id: Identity, action: &str, reason: &str, lobby_id: Option<u64>, expires_at: Option<Timestamp>) {
+    log::info!("Moderation: {} {} {} ({})", ctx.sender, action, target_id, reason);
+    ctx.db.moderation_audit().insert(ModerationAudit {
+        audit_id: 0,
+        actor_id: ctx.sender,
+        target_id,
+        action: action.to_string(),
+        reason: reason.to_string(),
+        lobby_id,
+        expires_at,
+        created_at: ctx.timestamp,
+    });
+}
+
+/// Timestamp `duration_secs` from now, or None for an open-ended sanction.
+fn expiry_after(ctx: &ReducerContext, duration_secs: Option<u64>) -> Result<Option<Timestamp>, String> {
+    let expires_at = expiry_micros_after(ctx.timestamp.to_micros_since_unix_epoch(), duration_secs)?;
+    Ok(expires_at.map(Timestamp::from_micros_since_unix_epoch))
 }
 
 #[reducer(init)]
@@ -156,6 +1824,8 @@ pub fn init(ctx: &ReducerContext) {
                 difficulty: "Easy".to_string(),
                 quality_score: 0,
                 origin_agent: None,
+                rating: item_rating_for_label("Easy"),
+                rated_answers: 0,
             },
             Question {
                 question_id: 0,
@@ -166,6 +1836,8 @@ pub fn init(ctx: &ReducerContext) {
                 difficulty: "Medium".to_string(),
                 quality_score: 0,
                 origin_agent: None,
+                rating: item_rating_for_label("Medium"),
+                rated_answers: 0,
             },
             Question {
                 question_id: 0,
@@ -176,6 +1848,8 @@ pub fn init(ctx: &ReducerContext) {
                 difficulty: "Hard".to_string(),
                 quality_score: 0,
                 origin_agent: None,
+                rating: item_rating_for_label("Hard"),
+                rated_answers: 0,
             },
             Question {
                 question_id: 0,
@@ -186,6 +1860,8 @@ pub fn init(ctx: &ReducerContext) {
                 difficulty: "Medium".to_string(),
                 quality_score: 0,
                 origin_agent: None,
+                rating: item_rating_for_label("Medium"),
+                rated_answers: 0,
             },
             Question {
                 question_id: 0,
@@ -196,6 +1872,8 @@ pub fn init(ctx: &ReducerContext) {
                 difficulty: "Medium".to_string(),
                 quality_score: 0,
                 origin_agent: None,
+                rating: item_rating_for_label("Medium"),
+                rated_answers: 0,
             },
             Question {
                 question_id: 0,
@@ -206,6 +1884,8 @@ pub fn init(ctx: &ReducerContext) {
                 difficulty: "Easy".to_string(),
                 quality_score: 0,
                 origin_agent: None,
+                rating: item_rating_for_label("Easy"),
+                rated_answers: 0,
             },
             Question {
                 question_id: 0,
@@ -216,6 +1896,8 @@ pub fn init(ctx: &ReducerContext) {
                 difficulty: "Hard".to_string(),
                 quality_score: 0,
                 origin_agent: None,
+                rating: item_rating_for_label("Hard"),
+                rated_answers: 0,
             },
             Question {
                 question_id: 0,
@@ -226,6 +1908,8 @@ pub fn init(ctx: &ReducerContext) {
                 difficulty: "Medium".to_string(),
                 quality_score: 0,
                 origin_agent: None,
+                rating: item_rating_for_label("Medium"),
+                rated_answers: 0,
             },
             Question {
                 question_id: 0,
@@ -236,6 +1920,8 @@ pub fn init(ctx: &ReducerContext) {
                 difficulty: "Easy".to_string(),
                 quality_score: 0,
                 origin_agent: None,
+                rating: item_rating_for_label("Easy"),
+                rated_answers: 0,
             },
             Question {
                 question_id: 0,
@@ -246,6 +1932,8 @@ pub fn init(ctx: &ReducerContext) {
                 difficulty: "Medium".to_string(),
                 quality_score: 0,
                 origin_agent: None,
+                rating: item_rating_for_label("Medium"),
+                rated_answers: 0,
             },
         ];
 
@@ -254,25 +1942,159 @@ pub fn init(ctx: &ReducerContext) {
         }
         log::info!("Bootstrapped question bank with initial questions");
     }
+
+    // Whoever publishes the module administers it
+    if ctx.db.admin().admin_id().find(ctx.sender).is_none() {
+        ctx.db.admin().insert(Admin { admin_id: ctx.sender, granted_at: ctx.timestamp });
+        log::info!("Granted admin to module publisher {}", ctx.sender);
+    }
+
+    if ctx.db.guest_token_issuer().count() == 0 {
+        ctx.db.guest_token_issuer().insert(GuestTokenIssuer {
+            issuer: DEFAULT_GUEST_TOKEN_ISSUER.to_string(),
+            added_by: ctx.sender,
+            added_at: ctx.timestamp,
+        });
+    }
+
+    if ctx.db.chat_purge_schedule().count() == 0 {
+        ctx.db.chat_purge_schedule().insert(ChatPurgeSchedule {
+            scheduled_id: 0,
+            scheduled_at: TimeDuration::from_micros(CHAT_PURGE_INTERVAL_MICROS).into(),
+        });
+    }
+
+    if ctx.db.highlight_schedule().count() == 0 {
+        ctx.db.highlight_schedule().insert(HighlightSchedule {
+            scheduled_id: 0,
+            scheduled_at: TimeDuration::from_micros(HIGHLIGHT_SCAN_INTERVAL_MICROS).into(),
+        });
+    }
+
+    if ctx.db.season().iter().all(|s| s.ended_at.is_some()) {
+        ctx.db.season().insert(Season {
+            season_id: 0,
+            name: "Season 1".to_string(),
+            started_at: ctx.timestamp,
+            ended_at: None,
+            soft_reset_factor: None,
+        });
+        log::info!("Opened the first season");
+    }
 }
 
 #[reducer(client_connected)]
-pub fn connect(_ctx: &ReducerContext) {
-    log::info!("Client connected: {}", _ctx.sender);
+pub fn connect(ctx: &ReducerContext) {
+    log::info!("Client connected: {}", ctx.sender);
+    if let Some(mut player) = ctx.db.player().player_id().find(ctx.sender) {
+        player.online = true;
+        player.last_seen = ctx.timestamp;
+        ctx.db.player().player_id().update(player);
+    }
+
+    // Resume into the lobby the player dropped out of, if they still hold a seat in it
+    if let Some(session) = ctx.db.player_session().player_id().find(ctx.sender) {
+        let still_seated = |lobby_id: u64| {
+            ctx.db.lobby().lobby_id().find(lobby_id).is_some_and(|l| lobby_is_open(&l))
+                && ctx.db.lobby_member().lobby_id().filter(lobby_id).any(|m| m.player_id == ctx.sender)
+        };
+        match resume_point(session.lobby_id, session.round_id, still_seated) {
+            Some((lobby_id, round_id)) => log::info!("Player {} resuming lobby {} at round {:?}", ctx.sender, lobby_id, round_id),
+            None if session.lobby_id.is_some() => set_player_session(ctx, ctx.sender, None, None),
+            None => {}
+        }
+    }
 }
 
 #[reducer(client_disconnected)]
-pub fn disconnect(_ctx: &ReducerContext) {
-    log::info!("Client disconnected: {}", _ctx.sender);
+pub fn disconnect(ctx: &ReducerContext) {
+    log::info!("Client disconnected: {}", ctx.sender);
+    if let Some(mut player) = ctx.db.player().player_id().find(ctx.sender) {
+        player.online = false;
+        player.last_seen = ctx.timestamp;
+        ctx.db.player().player_id().update(player);
+
+        // Hosts hand over straight away when another player is online to take over; otherwise
+        // the lobby waits out the grace period in case the host comes back
+        let hosted: Vec<Lobby> = ctx.db.lobby().host_id().filter(ctx.sender).filter(lobby_is_open).collect();
+        for lobby in hosted {
+            let change = on_host_disconnect(&lobby_seats(ctx, lobby.lobby_id), ctx.sender);
+            apply_host_change(ctx, lobby, change);
+        }
+
+        // Only the latest disconnect counts: an older timer would cut a later grace period short
+        ctx.db.lobby_departure().player_id().delete(ctx.sender);
+        ctx.db.lobby_departure().insert(LobbyDeparture {
+            scheduled_id: 0,
+            scheduled_at: (ctx.timestamp + TimeDuration::from_micros(DISCONNECT_GRACE_PERIOD_MICROS)).into(),
+            player_id: ctx.sender,
+        });
+    }
+}
+
+/// Settles a player who has not come back within the grace period: lobbies they still host pass
+/// to another player or are abandoned, and they are dropped from every waiting lobby they are in.
+#[reducer]
+pub fn remove_disconnected_player(ctx: &ReducerContext, departure: LobbyDeparture) -> Result<(), String> {
+    if ctx.sender != ctx.identity() {
+        return Err("remove_disconnected_player may only be invoked by the scheduler".to_string());
+    }
+    let Some(player) = ctx.db.player().player_id().find(departure.player_id) else {
+        return Ok(());
+    };
+    if player.online {
+        log::info!("Player {} reconnected within the grace period", player.name);
+    }
+
+    let hosted: Vec<Lobby> = ctx.db.lobby().host_id().filter(player.player_id).filter(lobby_is_open).collect();
+    for lobby in hosted {
+        let change = after_host_grace_period(&lobby_seats(ctx, lobby.lobby_id), player.player_id, player.online);
+        apply_host_change(ctx, lobby, change);
+    }
+
+    let memberships: Vec<(u64, bool)> = ctx.db.lobby_member().player_id().filter(player.player_id)
+        .map(|m| (m.lobby_id, ctx.db.lobby().lobby_id().find(m.lobby_id).is_some_and(|l| l.status == LOBBY_STATUS_WAITING)))
+        .collect();
+    for lobby_id in seats_to_vacate(player.online, &memberships) {
+        remove_lobby_member(ctx, lobby_id, player.player_id);
+        log::info!("Removed disconnected player {} from waiting lobby {}", player.name, lobby_id);
+    }
+    Ok(())
 }
 
 #[reducer]
 pub fn join_lobby(ctx: &ReducerContext, lobby_name: Option<String>) -> Result<(), String> {
+    join_or_create_lobby(ctx, lobby_name, None)
+}
+
+#[reducer]
+pub fn join_topic_lobby(ctx: &ReducerContext, topic: String, lobby_name: Option<String>) -> Result<(), String> {
+    let topic = topic.trim().to_string();
+    if topic.is_empty() {
+        return Err("Topic cannot be empty".to_string());
+    }
+    if ctx.db.question_bank().topic().filter(&topic).next().is_none() {
+        return Err(format!("No questions available for topic {}", topic));
+    }
+    join_or_create_lobby(ctx, lobby_name, Some(topic))
+}
+
+fn join_or_create_lobby(ctx: &ReducerContext, lobby_name: Option<String>, topic: Option<String>) -> Result<(), String> {
     let player_id = ctx.sender;
 
+    if let Some(ban) = active_ban(ctx, player_id) {
+        return Err(match ban.expires_at {
+            Some(expires_at) => format!("You are banned until {}: {}", expires_at, ban.reason),
+            None => format!("You are banned: {}", ban.reason),
+        });
+    }
+
     // Check if player name exists using the index
-    if let Some(existing_player) = ctx.db.player().player_id().find(&player_id) {
+    if let Some(mut existing_player) = ctx.db.player().player_id().find(player_id) {
         log::info!("Existing player {} joining lobby", existing_player.name);
+        existing_player.online = true;
+        existing_player.last_seen = ctx.timestamp;
+        ctx.db.player().player_id().update(existing_player);
     } else {
         // Generate unique player name
         let base_name = format!("Player_{}", &player_id.to_string()[..8]);
@@ -290,17 +2112,31 @@ pub fn join_lobby(ctx: &ReducerContext, lobby_name: Option<String>) -> Result<()
             player_id,
             name: player_name.clone(),
             score: 0,
-            elo: 1200, // Initialize Elo to a default starting value
+            season_score: 0,
+            elo: INITIAL_ELO, // Initialize Elo to a default starting value
+            games_played: 0,
+            provisional: true,
+            avatar: DEFAULT_AVATAR.to_string(),
+            online: true,
+            last_seen: ctx.timestamp,
         }) {
             Ok(_) => log::info!("Created new player: {}", player_name),
             Err(_) => return Err("Failed to create player - name taken".to_string()),
         }
+        if ctx.db.player_glicko().player_id().find(player_id).is_none() {
+            ctx.db.player_glicko().insert(PlayerGlicko::new(player_id));
+        }
     }
 
-    // Find waiting lobby using status index
+    // Matchmaking: join the waiting lobby for the same topic whose host is closest in rating
+    let player_rating = active_rating(ctx, player_id, topic.as_deref());
     if let Some(lobby) = ctx.db.lobby()
-        .iter()
-        .find(|l| l.status == LOBBY_STATUS_WAITING) {
+        .status()
+        .filter(LOBBY_STATUS_WAITING)
+        .filter(|l| l.topic == topic)
+        .filter(|l| ctx.db.player().player_id().find(l.host_id).is_some_and(|h| h.online))
+        .min_by_key(|l| (active_rating(ctx, l.host_id, topic.as_deref()) - player_rating).abs()) {
+        add_lobby_member(ctx, lobby.lobby_id, player_id, MEMBER_ROLE_PLAYER);
         log::info!("Player {} joined existing lobby {}", player_id, lobby.lobby_id);
         return Ok(());
     }
@@ -312,10 +2148,21 @@ pub fn join_lobby(ctx: &ReducerContext, lobby_name: Option<String>) -> Result<()
         status: LOBBY_STATUS_WAITING.to_string(),
         host_id: player_id,
         next_round_is_lightning: false,
+        topic,
+        question_mode: QUESTION_MODE_ADAPTIVE.to_string(),
+        fixed_difficulty: None,
+        crowd_meter_mode: CROWD_METER_LIVE.to_string(),
+        crowd_meter_delay_secs: 0,
+        game_mode: GAME_MODE_CLASSIC.to_string(),
+        team_count: MIN_TEAM_COUNT,
+        team_scoring: TEAM_SCORING_SUM.to_string(),
+        royale_lives: 1,
+        royale_round_cap: None,
     };
 
     match ctx.db.lobby().try_insert(new_lobby) {
         Ok(lobby) => {
+            add_lobby_member(ctx, lobby.lobby_id, player_id, MEMBER_ROLE_PLAYER);
             log::info!("Player {} created new lobby {}", player_id, lobby.lobby_id);
             Ok(())
         },
@@ -326,7 +2173,7 @@ pub fn join_lobby(ctx: &ReducerContext, lobby_name: Option<String>) -> Result<()
 #[reducer]
 pub fn start_game(ctx: &ReducerContext, lobby_id: u64) -> Result<(), String> {
     // Find lobby using primary key index
-    let lobby = ctx.db.lobby().lobby_id().find(&lobby_id)
+    let lobby = ctx.db.lobby().lobby_id().find(lobby_id)
         .ok_or_else(|| format!("Lobby {} not found", lobby_id))?;
 
     // Security check using proper Identity comparison
@@ -339,56 +2186,349 @@ pub fn start_game(ctx: &ReducerContext, lobby_id: u64) -> Result<(), String> {
     // For now, we assume start_game is only for the first round.
     // Logic for subsequent rounds might need a different reducer or flow.
     if lobby.status != LOBBY_STATUS_WAITING {
-        return Err(format!("Lobby {} is not in waiting status (current: {})", lobby_id, lobby.status));
+        return Err(format!("Lobby {} is not in waiting status (current: {})", lobby_id, lobby.status));
+    }
+
+    if lobby.game_mode == GAME_MODE_TEAMS {
+        let player_count = ctx.db.lobby_member().lobby_id().filter(lobby_id)
+            .filter(|m| m.role == MEMBER_ROLE_PLAYER || m.joins_next_round)
+            .count();
+        if player_count < lobby.team_count as usize {
+            return Err(format!("Lobby {} needs at least {} players for {} teams", lobby_id, lobby.team_count, lobby.team_count));
+        }
+    }
+    if lobby.game_mode == GAME_MODE_BATTLE_ROYALE {
+        let player_count = ctx.db.lobby_member().lobby_id().filter(lobby_id)
+            .filter(|m| m.role == MEMBER_ROLE_PLAYER || m.joins_next_round)
+            .count();
+        if player_count < 2 {
+            return Err(format!("Lobby {} needs at least 2 players for a battle royale", lobby_id));
+        }
+    }
+
+    // Check if this round should be a lightning round
+    let mut current_lobby = lobby.clone(); // Clone to modify for next_round_is_lightning flag
+    let make_lightning_round = if current_lobby.next_round_is_lightning {
+        current_lobby.next_round_is_lightning = false; // Reset the flag
+        true
+    } else {
+        false
+    };
+
+    // Update lobby status to in_game and reset next_round_is_lightning if it was used
+    current_lobby.status = LOBBY_STATUS_IN_GAME.to_string();
+    promote_waiting_spectators(ctx, lobby_id);
+    if lobby.game_mode == GAME_MODE_TEAMS {
+        assign_teams(ctx, &lobby);
+    }
+    if lobby.game_mode == GAME_MODE_BATTLE_ROYALE {
+        let players: Vec<Identity> = ctx.db.lobby_member().lobby_id().filter(lobby_id)
+            .filter(|m| m.role == MEMBER_ROLE_PLAYER)
+            .map(|m| m.player_id)
+            .collect();
+        for player_id in players {
+            ctx.db.royale_standing().insert(RoyaleStanding {
+                standing_id: 0,
+                lobby_id,
+                player_id,
+                lives: lobby.royale_lives,
+                placement: None,
+                eliminated_in_round: None,
+            });
+        }
+    }
+    ctx.db.lobby().lobby_id().update(current_lobby.clone()); // Use current_lobby which has the updated next_round_is_lightning
+
+    // Select a question for the lobby's question mode
+    let question_count = ctx.db.question_bank().count();
+    if question_count == 0 {
+        return Err("No questions available in the question bank".to_string());
+    }
+
+    let question = select_question(ctx, &lobby)?;
+
+    // Create first round
+    let new_round = ActiveRound {
+        round_id: 0,
+        lobby_id,
+        question_id: question.question_id,
+        start_time: ctx.timestamp,
+        status: ROUND_STATUS_WAITING.to_string(),
+        is_lightning: make_lightning_round, // Set based on lobby flag
+    };
+
+    match ctx.db.active_round().try_insert(new_round) {
+        Ok(round) => {
+            log::info!("Started new round {} in lobby {}", round.round_id, lobby_id);
+            advance_lobby_sessions(ctx, lobby_id, round.round_id);
+            log_round_opened(ctx, &round);
+            // Schedule the first lightning tick for this lobby (temporarily disabled)
+            // let delay_micros = 120 * 1_000_000i64;
+            // let schedule_at = Timestamp::from_micros_since_unix_epoch(ctx.timestamp.to_micros_since_unix_epoch() + delay_micros);
+            // ctx.schedule_event(schedule_at, "lightning_tick", (lobby_id,));
+            log::info!("(Scheduling disabled) Would schedule first lightning_tick for lobby {}", lobby_id);
+            Ok(())
+        },
+        Err(e) => Err(format!("Failed to create round: {}", e))
+    }
+}
+
+#[reducer]
+pub fn start_next_round(ctx: &ReducerContext, lobby_id: u64) -> Result<(), String> {
+    let mut lobby = ctx.db.lobby().lobby_id().find(lobby_id)
+        .ok_or_else(|| format!("Lobby {} not found", lobby_id))?;
+
+    if lobby.host_id != ctx.sender {
+        return Err(format!("Only the host can start the next round. You are not the host of lobby {}", lobby_id));
+    }
+
+    if lobby.status != LOBBY_STATUS_IN_GAME {
+        return Err(format!("Lobby {} is not in game (current: {})", lobby_id, lobby.status));
+    }
+
+    if let Some(open_round) = ctx.db.active_round().lobby_id().filter(lobby_id).find(|r| r.status != ROUND_STATUS_FINISHED) {
+        return Err(format!("Round {} in lobby {} is not finished yet", open_round.round_id, lobby_id));
+    }
+
+    let question = select_question(ctx, &lobby)?;
+
+    promote_waiting_spectators(ctx, lobby_id);
+    if lobby.game_mode == GAME_MODE_TEAMS {
+        assign_teams(ctx, &lobby);
+    }
+
+    let is_lightning = lobby.next_round_is_lightning;
+    if is_lightning {
+        lobby.next_round_is_lightning = false;
+        ctx.db.lobby().lobby_id().update(lobby);
+    }
+
+    let round = ctx.db.active_round().try_insert(ActiveRound {
+        round_id: 0,
+        lobby_id,
+        question_id: question.question_id,
+        start_time: ctx.timestamp,
+        status: ROUND_STATUS_WAITING.to_string(),
+        is_lightning,
+    }).map_err(|e| format!("Failed to create round: {}", e))?;
+
+    log::info!("Started round {} in lobby {} with question {}", round.round_id, lobby_id, question.question_id);
+    advance_lobby_sessions(ctx, lobby_id, round.round_id);
+    log_round_opened(ctx, &round);
+    Ok(())
+}
+
+#[reducer]
+pub fn set_question_mode(ctx: &ReducerContext, lobby_id: u64, question_mode: String, fixed_difficulty: Option<String>) -> Result<(), String> {
+    let mut lobby = ctx.db.lobby().lobby_id().find(lobby_id)
+        .ok_or_else(|| format!("Lobby {} not found", lobby_id))?;
+
+    if lobby.host_id != ctx.sender {
+        return Err(format!("Only the host can change lobby settings. You are not the host of lobby {}", lobby_id));
+    }
+
+    if lobby.status == LOBBY_STATUS_FINISHED {
+        return Err(format!("Lobby {} is already finished", lobby_id));
+    }
+
+    if question_mode != QUESTION_MODE_ADAPTIVE && question_mode != QUESTION_MODE_FIXED && question_mode != QUESTION_MODE_MIXED {
+        return Err(format!("Invalid question mode: {}", question_mode));
+    }
+
+    let fixed_difficulty = if question_mode == QUESTION_MODE_FIXED {
+        let difficulty = fixed_difficulty.ok_or("Fixed question mode requires a difficulty")?;
+        let band = parse_difficulty(&difficulty)
+            .ok_or_else(|| format!("Invalid difficulty: {}", difficulty))?;
+        Some(band.to_string())
+    } else {
+        None
+    };
+
+    log::info!("Lobby {} question mode set to {} ({:?})", lobby_id, question_mode, fixed_difficulty);
+    lobby.question_mode = question_mode;
+    lobby.fixed_difficulty = fixed_difficulty;
+    ctx.db.lobby().lobby_id().update(lobby);
+    Ok(())
+}
+
+#[reducer]
+pub fn transfer_host(ctx: &ReducerContext, lobby_id: u64, new_host_id: Identity) -> Result<(), String> {
+    let mut lobby = ctx.db.lobby().lobby_id().find(lobby_id)
+        .ok_or_else(|| format!("Lobby {} not found", lobby_id))?;
+    if lobby.host_id != ctx.sender {
+        return Err("Only the host can transfer the host role".to_string());
+    }
+    if !lobby_is_open(&lobby) {
+        return Err(format!("Lobby {} is {}", lobby_id, lobby.status));
+    }
+    if !ctx.db.lobby_member().lobby_id().filter(lobby_id).any(|m| m.player_id == new_host_id) {
+        return Err(format!("Player {} is not in lobby {}", new_host_id, lobby_id));
+    }
+
+    log::info!("Lobby {} host transferred from {} to {}", lobby_id, ctx.sender, new_host_id);
+    lobby.host_id = new_host_id;
+    ctx.db.lobby().lobby_id().update(lobby);
+    Ok(())
+}
+
+#[reducer]
+pub fn leave_lobby(ctx: &ReducerContext, lobby_id: u64) -> Result<(), String> {
+    let lobby = ctx.db.lobby().lobby_id().find(lobby_id)
+        .ok_or_else(|| format!("Lobby {} not found", lobby_id))?;
+    if !ctx.db.lobby_member().lobby_id().filter(lobby_id).any(|m| m.player_id == ctx.sender) {
+        return Err(format!("Not a member of lobby {}", lobby_id));
+    }
+    if !lobby_is_open(&lobby) {
+        return Err(format!("Lobby {} is {}", lobby_id, lobby.status));
+    }
+
+    log::info!("Player {} left lobby {}", ctx.sender, lobby_id);
+    remove_lobby_member(ctx, lobby_id, ctx.sender);
+    Ok(())
+}
+
+#[reducer]
+pub fn spectate_lobby(ctx: &ReducerContext, lobby_id: u64) -> Result<(), String> {
+    if let Some(ban) = active_ban(ctx, ctx.sender) {
+        return Err(format!("You are banned: {}", ban.reason));
+    }
+    let lobby = ctx.db.lobby().lobby_id().find(lobby_id)
+        .ok_or_else(|| format!("Lobby {} not found", lobby_id))?;
+    if !lobby_is_open(&lobby) {
+        return Err(format!("Lobby {} is {}", lobby_id, lobby.status));
+    }
+    if ctx.db.player().player_id().find(ctx.sender).is_none() {
+        return Err("Player not found".to_string());
+    }
+    if ctx.db.lobby_member().lobby_id().filter(lobby_id).any(|m| m.player_id == ctx.sender) {
+        return Err(format!("Already in lobby {}", lobby_id));
+    }
+
+    add_lobby_member(ctx, lobby_id, ctx.sender, MEMBER_ROLE_SPECTATOR);
+    log::info!("Player {} is spectating lobby {}", ctx.sender, lobby_id);
+    Ok(())
+}
+
+/// Turns a spectator into a player: immediately before the game starts, otherwise from the next round.
+#[reducer]
+pub fn join_live(ctx: &ReducerContext, lobby_id: u64) -> Result<(), String> {
+    let lobby = ctx.db.lobby().lobby_id().find(lobby_id)
+        .ok_or_else(|| format!("Lobby {} not found", lobby_id))?;
+    if !lobby_is_open(&lobby) {
+        return Err(format!("Lobby {} is {}", lobby_id, lobby.status));
+    }
+    let mut member = ctx.db.lobby_member().lobby_id().filter(lobby_id)
+        .find(|m| m.player_id == ctx.sender && m.role == MEMBER_ROLE_SPECTATOR)
+        .ok_or_else(|| format!("Not spectating lobby {}", lobby_id))?;
+    match live_join(lobby.status != LOBBY_STATUS_WAITING, lobby.game_mode == GAME_MODE_BATTLE_ROYALE) {
+        Some(LiveJoin::Now) => {
+            member.role = MEMBER_ROLE_PLAYER.to_string();
+            log::info!("Spectator {} joined lobby {} as a player", ctx.sender, lobby_id);
+        }
+        Some(LiveJoin::NextRound) => {
+            member.joins_next_round = true;
+            log::info!("Spectator {} will join lobby {} at the next round", ctx.sender, lobby_id);
+        }
+        None => return Err(format!("Lobby {} is playing a battle royale; nobody can join once it has started", lobby_id)),
+    }
+    ctx.db.lobby_member().member_id().update(member);
+    Ok(())
+}
+
+#[reducer]
+pub fn set_crowd_meter_mode(ctx: &ReducerContext, lobby_id: u64, mode: String, delay_secs: Option<u32>) -> Result<(), String> {
+    let mut lobby = ctx.db.lobby().lobby_id().find(lobby_id)
+        .ok_or_else(|| format!("Lobby {} not found", lobby_id))?;
+    if lobby.host_id != ctx.sender {
+        return Err(format!("Only the host can change lobby settings. You are not the host of lobby {}", lobby_id));
+    }
+    if !lobby_is_open(&lobby) {
+        return Err(format!("Lobby {} is {}", lobby_id, lobby.status));
+    }
+
+    let (mode, delay_secs) = parse_mode(&mode, delay_secs)?;
+    log::info!("Lobby {} crowd meter mode set to {} (delay {}s)", lobby_id, mode, delay_secs);
+    lobby.crowd_meter_mode = mode.to_string();
+    lobby.crowd_meter_delay_secs = delay_secs;
+    ctx.db.lobby().lobby_id().update(lobby);
+    Ok(())
+}
+
+#[reducer]
+pub fn set_game_mode(ctx: &ReducerContext, lobby_id: u64, game_mode: String) -> Result<(), String> {
+    let mut lobby = ctx.db.lobby().lobby_id().find(lobby_id)
+        .ok_or_else(|| format!("Lobby {} not found", lobby_id))?;
+    if lobby.host_id != ctx.sender {
+        return Err(format!("Only the host can change lobby settings. You are not the host of lobby {}", lobby_id));
+    }
+    if lobby.status != LOBBY_STATUS_WAITING {
+        return Err(format!("The game mode can only be changed before the game starts (lobby {} is {})", lobby_id, lobby.status));
+    }
+
+    let game_mode = game_mode.trim().to_lowercase();
+    let modes = [GAME_MODE_CLASSIC, GAME_MODE_TEAMS, GAME_MODE_BATTLE_ROYALE, GAME_MODE_BUZZER];
+    if !modes.contains(&game_mode.as_str()) {
+        return Err(format!("Invalid game mode: {}. Expected one of: {}", game_mode, modes.join(", ")));
+    }
+
+    log::info!("Lobby {} game mode set to {}", lobby_id, game_mode);
+    lobby.game_mode = game_mode;
+    ctx.db.lobby().lobby_id().update(lobby);
+    Ok(())
+}
+
+#[reducer]
+pub fn set_team_settings(ctx: &ReducerContext, lobby_id: u64, team_count: u32, team_scoring: String) -> Result<(), String> {
+    let mut lobby = ctx.db.lobby().lobby_id().find(lobby_id)
+        .ok_or_else(|| format!("Lobby {} not found", lobby_id))?;
+    if lobby.host_id != ctx.sender {
+        return Err(format!("Only the host can change lobby settings. You are not the host of lobby {}", lobby_id));
+    }
+    if lobby.status != LOBBY_STATUS_WAITING {
+        return Err(format!("Teams can only be changed before the game starts (lobby {} is {})", lobby_id, lobby.status));
+    }
+
+    let team_scoring = parse_team_settings(team_count, &team_scoring)?;
+    log::info!("Lobby {} plays {} teams scored by {}", lobby_id, team_count, team_scoring);
+    lobby.team_count = team_count;
+    lobby.team_scoring = team_scoring.to_string();
+    ctx.db.lobby().lobby_id().update(lobby);
+    Ok(())
+}
+
+#[reducer]
+pub fn set_battle_royale_settings(ctx: &ReducerContext, lobby_id: u64, lives: u32, round_cap: Option<u32>) -> Result<(), String> {
+    let mut lobby = ctx.db.lobby().lobby_id().find(lobby_id)
+        .ok_or_else(|| format!("Lobby {} not found", lobby_id))?;
+    if lobby.host_id != ctx.sender {
+        return Err(format!("Only the host can change lobby settings. You are not the host of lobby {}", lobby_id));
+    }
+    if lobby.status != LOBBY_STATUS_WAITING {
+        return Err(format!("Battle royale settings can only be changed before the game starts (lobby {} is {})", lobby_id, lobby.status));
     }
 
-    // Check if this round should be a lightning round
-    let mut current_lobby = lobby.clone(); // Clone to modify for next_round_is_lightning flag
-    let make_lightning_round = if current_lobby.next_round_is_lightning {
-        current_lobby.next_round_is_lightning = false; // Reset the flag
-        true
-    } else {
-        false
-    };
+    validate_royale_settings(lives, round_cap)?;
 
-    // Update lobby status to in_game and reset next_round_is_lightning if it was used
-    current_lobby.status = LOBBY_STATUS_IN_GAME.to_string();
-    ctx.db.lobby().try_insert(current_lobby.clone()) // Use current_lobby which has the updated next_round_is_lightning
-        .map_err(|e| format!("Failed to update lobby status/flag: {}", e))?;
+    log::info!("Lobby {} battle royale: {} lives, round cap {:?}", lobby_id, lives, round_cap);
+    lobby.royale_lives = lives;
+    lobby.royale_round_cap = round_cap;
+    ctx.db.lobby().lobby_id().update(lobby);
+    Ok(())
+}
 
-    // Select random question using timestamp-based randomization
-    let question_count = ctx.db.question_bank().count();
-    if question_count == 0 {
-        return Err("No questions available in the question bank".to_string());
+#[reducer]
+pub fn release_crowd_meter(ctx: &ReducerContext, release: CrowdMeterRelease) -> Result<(), String> {
+    if ctx.sender != ctx.identity() {
+        return Err("release_crowd_meter may only be invoked by the scheduler".to_string());
     }
-
-    // Use timestamp value directly for randomization
-    let random_index = (ctx.timestamp.to_micros_since_unix_epoch() % question_count as i64) as usize;
-    let question = ctx.db.question_bank().iter().nth(random_index)
-        .ok_or("Failed to select random question")?;
-
-    // Create first round
-    let new_round = ActiveRound {
-        round_id: 0,
-        lobby_id,
-        question_id: question.question_id,
-        start_time: ctx.timestamp,
-        status: ROUND_STATUS_WAITING.to_string(),
-        is_lightning: make_lightning_round, // Set based on lobby flag
+    let Some(round) = ctx.db.active_round().round_id().find(release.round_id) else {
+        return Ok(());
     };
-
-    match ctx.db.active_round().try_insert(new_round) {
-        Ok(round) => {
-            log::info!("Started new round {} in lobby {}", round.round_id, lobby_id);
-            // Schedule the first lightning tick for this lobby (temporarily disabled)
-            // let delay_micros = 120 * 1_000_000i64;
-            // let schedule_at = Timestamp::from_micros_since_unix_epoch(ctx.timestamp.to_micros_since_unix_epoch() + delay_micros);
-            // ctx.schedule_event(schedule_at, "lightning_tick", (lobby_id,));
-            log::info!("(Scheduling disabled) Would schedule first lightning_tick for lobby {}", lobby_id);
-            Ok(())
-        },
-        Err(e) => Err(format!("Failed to create round: {}", e))
+    // A scored round already shows its final counts; a late snapshot must not roll them back
+    if round.status == ROUND_STATUS_IN_PROGRESS {
+        publish_crowd_meter(ctx, &round, &release.counts, false);
     }
+    Ok(())
 }
 
 // #[reducer] // Temporarily disable lightning_tick reducer to avoid missing schedule feature
@@ -407,17 +2547,19 @@ pub fn submit_answer(ctx: &ReducerContext, round_id: u64, chosen_answer_index: u
     // }
 
     // Find round using primary key index
-    let round = ctx.db.active_round().round_id().find(&round_id)
+    let round = ctx.db.active_round().round_id().find(round_id)
         .ok_or_else(|| format!("Round {} not found", round_id))?;
 
     if round.status != ROUND_STATUS_IN_PROGRESS {
         return Err(format!("Round {} is not in progress (current status: {})", round_id, round.status));
     }
 
+    if is_spectator(ctx, round.lobby_id, ctx.sender) {
+        return Err("Spectators cannot submit answers".to_string());
+    }
+
     // Check for existing answer using indexes
-    if let Some(existing) = ctx.db.answer()
-        .iter()
-        .find(|a| a.round_id == round_id && a.player_id == ctx.sender) {
+    if let Some(existing) = ctx.db.answer().round_player().filter((round_id, ctx.sender)).next() {
         return Err(format!("Already submitted answer {} for round {}", existing.answer_id, round_id));
     }
 
@@ -428,32 +2570,22 @@ pub fn submit_answer(ctx: &ReducerContext, round_id: u64, chosen_answer_index: u
         player_id: ctx.sender,
         chosen_answer_index, // Use the provided index
         score: None,
+        submitted_at: ctx.timestamp,
     };
 
     // Try to insert the answer
     match ctx.db.answer().try_insert(new_answer) {
-        Ok(_) => {
+        Ok(answer) => {
             log::info!("Player {} submitted answer index {} for round {}", ctx.sender, chosen_answer_index, round_id);
+            log_game_event(ctx, &round, GAME_EVENT_ANSWER_SUBMITTED, None, None, None);
 
-            // Update CrowdMeterStats
-            let stat_entry_opt = CrowdMeterStats::iter(ctx.db)
-                .find(|s| s.round_id == round_id && s.answer_index == chosen_answer_index);
+            // Update CrowdMeterStats through the (round_id, answer_index) index
+            record_answer(&mut CrowdMeterStatsTable(ctx), round_id, chosen_answer_index);
 
-            if let Some(mut existing_stat) = stat_entry_opt {
-                existing_stat.count += 1;
-                if let Err(e) = ctx.db.crowd_meter_stats().try_insert(existing_stat) {
-                    log::error!("Failed to update CrowdMeterStats for round {}, index {}: {}", round_id, chosen_answer_index, e);
-                    // Not failing the whole reducer for this, but logging error.
-                }
-            } else {
-                let new_stat = CrowdMeterStats {
-                    round_id,
-                    answer_index: chosen_answer_index,
-                    count: 1,
-                };
-                if let Err(e) = ctx.db.crowd_meter_stats().try_insert(new_stat) {
-                    log::error!("Failed to insert new CrowdMeterStats for round {}, index {}: {}", round_id, chosen_answer_index, e);
-                    // Not failing the whole reducer for this, but logging error.
+            if let Some(lobby) = ctx.db.lobby().lobby_id().find(round.lobby_id) {
+                publish_crowd_meter_for_mode(ctx, &lobby, &round, ctx.sender);
+                if lobby.game_mode == GAME_MODE_BUZZER {
+                    judge_buzz(ctx, &lobby, &round, answer);
                 }
             }
             Ok(())
@@ -465,11 +2597,11 @@ pub fn submit_answer(ctx: &ReducerContext, round_id: u64, chosen_answer_index: u
 #[reducer]
 pub fn score_round(ctx: &ReducerContext, round_id: u64) -> Result<(), String> {
     // Find round using primary key index
-    let round = ctx.db.active_round().round_id().find(&round_id)
+    let round = ctx.db.active_round().round_id().find(round_id)
         .ok_or_else(|| format!("Round {} not found", round_id))?;
 
     // Find lobby using primary key index
-    let lobby = ctx.db.lobby().lobby_id().find(&round.lobby_id)
+    let lobby = ctx.db.lobby().lobby_id().find(round.lobby_id)
         .ok_or_else(|| format!("Lobby {} not found", round.lobby_id))?;
 
     // Security check using proper Identity comparison
@@ -484,14 +2616,22 @@ pub fn score_round(ctx: &ReducerContext, round_id: u64) -> Result<(), String> {
     }
 
     // Get the question using primary key index
-    let question = ctx.db.question_bank().question_id().find(&round.question_id)
+    let mut question = ctx.db.question_bank().question_id().find(round.question_id)
         .ok_or_else(|| format!("Question {} not found", round.question_id))?;
 
+    // Buzzes were judged as they arrived, so the host only closes the round; like judge_buzz,
+    // this neither re-scores the buzzes nor calibrates the question
+    if lobby.game_mode == GAME_MODE_BUZZER {
+        let buzzes: Vec<Answer> = ctx.db.answer().round_id().filter(round_id).collect();
+        finish_scored_round(ctx, &round, &question, &buzzes);
+        log::info!("Buzzer round {} closed by the host", round_id);
+        return Ok(());
+    }
+
     // Update round status to scoring
     let mut scoring_round = round.clone();
     scoring_round.status = ROUND_STATUS_SCORING.to_string();
-    ctx.db.active_round().try_insert(scoring_round)
-        .map_err(|e| format!("Failed to update round status to scoring: {}", e))?;
+    ctx.db.active_round().round_id().update(scoring_round);
 
     // Score each answer
     let answers: Vec<Answer> = ctx.db.answer()
@@ -499,14 +2639,12 @@ pub fn score_round(ctx: &ReducerContext, round_id: u64) -> Result<(), String> {
         .filter(round_id)
         .collect();
 
-    for answer in answers {
-        // Calculate score based on correctness (case-insensitive comparison)
-        let mut points_for_correct = 10; // Base points
-        if round.is_lightning {
-            points_for_correct *= 2; // Double points for lightning round
-            log::info!("Lightning round! Player {} gets double points if correct.", answer.player_id);
-        }
+    // Item calibration: (player Elo, answered correctly) for every answer, see calibrate_item
+    let mut item_results: Vec<(i32, bool)> = Vec::new();
+    let mut scored_answers: Vec<Answer> = Vec::new();
+    let points_for_correct = points_for_correct(&round);
 
+    for answer in answers {
         let score = if answer.chosen_answer_index == 0 {
             points_for_correct
         } else {
@@ -516,26 +2654,36 @@ pub fn score_round(ctx: &ReducerContext, round_id: u64) -> Result<(), String> {
         // Update answer score
         let mut scored_answer = answer.clone();
         scored_answer.score = Some(score);
-        if let Err(e) = ctx.db.answer().try_insert(scored_answer) {
-            log::error!("Failed to update score for answer {}: {}", answer.answer_id, e);
-            continue; // Continue with other answers even if one fails
-        }
+        scored_answers.push(ctx.db.answer().answer_id().update(scored_answer));
+        log_game_event(ctx, &round, GAME_EVENT_ANSWER_SCORED, Some(answer.player_id), Some(answer.chosen_answer_index), Some(score as i64));
 
         // Update player's total score
-        if let Some(mut player) = ctx.db.player().player_id().find(&answer.player_id) {
+        if let Some(mut player) = ctx.db.player().player_id().find(answer.player_id) {
+            item_results.push((player.elo, score > 0));
+
             player.score += score;
+            player.season_score += score;
             // Note: Elo is not updated here; it will be updated at game end typically.
-            if let Err(e) = ctx.db.player().try_insert(player) {
-                log::error!("Failed to update score for player {}: {}", answer.player_id, e);
-            }
+            ctx.db.player().player_id().update(player);
         }
     }
 
-    // Mark round as finished
-    let mut finished_round = round.clone();
-    finished_round.status = ROUND_STATUS_FINISHED.to_string();
-    ctx.db.active_round().try_insert(finished_round)
-        .map_err(|e| format!("Failed to update round status to finished: {}", e))?;
+    (question.rating, question.rated_answers) = calibrate_item(question.rating, question.rated_answers, &item_results);
+    if question.rated_answers >= ITEM_CALIBRATION_ANSWERS {
+        // Calibrated: the displayed band follows the rating instead of the hand-set label
+        question.difficulty = difficulty_band(question.rating).to_string();
+    }
+
+    if lobby.game_mode == GAME_MODE_TEAMS {
+        score_team_round(ctx, &lobby, &scored_answers, points_for_correct);
+    }
+    finish_scored_round(ctx, &round, &question, &scored_answers);
+    ctx.db.question_bank().question_id().update(question);
+
+    if lobby.game_mode == GAME_MODE_BATTLE_ROYALE && settle_royale_round(ctx, &lobby, &round, &scored_answers) {
+        log::info!("Battle royale in lobby {} is over after round {}", lobby.lobby_id, round_id);
+        return finalize_game_and_update_elo(ctx, lobby.lobby_id);
+    }
 
     log::info!("Scored round {} successfully", round_id);
     Ok(())
@@ -545,7 +2693,7 @@ pub fn score_round(ctx: &ReducerContext, round_id: u64) -> Result<(), String> {
 pub fn finalize_game_and_update_elo(ctx: &ReducerContext, lobby_id: u64) -> Result<(), String> {
     log::info!("finalize_game_and_update_elo called for lobby_id: {}", lobby_id);
 
-    let lobby = ctx.db.lobby().lobby_id().find(&lobby_id)
+    let lobby = ctx.db.lobby().lobby_id().find(lobby_id)
         .ok_or_else(|| format!("Lobby {} not found for finalize_game", lobby_id))?;
 
     if lobby.host_id != ctx.sender {
@@ -556,61 +2704,597 @@ pub fn finalize_game_and_update_elo(ctx: &ReducerContext, lobby_id: u64) -> Resu
         return Err(format!("Lobby {} is not in_game (status: {}). Cannot finalize.", lobby_id, lobby.status));
     }
 
-    let mut player_participants: std::collections::HashMap<Identity, Player> = std::collections::HashMap::new();
+    // (topic, player, points) for every answer of the game, for the per-topic ratings
+    let mut topic_answers: Vec<(String, Identity, u32)> = Vec::new();
 
-    for round in ActiveRound::iter(ctx.db).filter(|r| r.lobby_id == lobby_id) {
-        for answer in Answer::iter(ctx.db).filter(|a| a.round_id == round.round_id) {
-            if !player_participants.contains_key(&answer.player_id) {
-                if let Some(player) = ctx.db.player().player_id().find(&answer.player_id) {
-                    player_participants.insert(answer.player_id, player.clone());
-                }
-            }
+    // Eliminated battle royale players watch as spectators, but are settled on their placement
+    let royale: std::collections::HashMap<Identity, RoyaleStanding> = ctx.db.royale_standing().lobby_id().filter(lobby_id)
+        .map(|s| (s.player_id, s))
+        .collect();
+
+    // Spectators are never rated, whatever they may have in the answer table
+    let spectators: Vec<Identity> = ctx.db.lobby_member().lobby_id().filter(lobby_id)
+        .filter(|m| m.role == MEMBER_ROLE_SPECTATOR)
+        .map(|m| m.player_id)
+        .collect();
+
+    // The game is ranked on this lobby's scoreboard, never on the players' lifetime points
+    let scoreboard: Vec<(Identity, u32)> = ctx.db.lobby_score().lobby_player().filter(lobby_id).map(|e| (e.player_id, e.points)).collect();
+    let royale_players: Option<Vec<Identity>> = (lobby.game_mode == GAME_MODE_BATTLE_ROYALE).then(|| royale.keys().copied().collect());
+    let players_vec: Vec<(Player, u32)> = settled_players(&scoreboard, &spectators, royale_players.as_deref()).into_iter()
+        .filter_map(|(player_id, points)| ctx.db.player().player_id().find(player_id).map(|p| (p, points)))
+        .collect();
+
+    for round in ctx.db.active_round().lobby_id().filter(lobby_id) {
+        let Some(topic) = ctx.db.question_bank().question_id().find(round.question_id).map(|q| q.topic) else {
+            continue;
+        };
+        for answer in ctx.db.answer().round_id().filter(round.round_id) {
+            topic_answers.push((topic.clone(), answer.player_id, answer.score.unwrap_or(0)));
         }
     }
 
-    if player_participants.len() < 2 {
+    if players_vec.len() < 2 {
         log::warn!("Lobby {} has fewer than 2 participants with answers. Skipping Elo update.", lobby_id);
+        clear_lobby_sessions(ctx, lobby_id);
         let mut final_lobby = lobby.clone();
         final_lobby.status = LOBBY_STATUS_FINISHED.to_string();
         final_lobby.next_round_is_lightning = false;
-        ctx.db.lobby().try_insert(final_lobby).map_err(|e| format!("Failed to set lobby {} to finished: {}", lobby_id, e))?;
+        ctx.db.lobby().lobby_id().update(final_lobby);
         return Ok(());
     }
 
-    let mut players_vec: Vec<Player> = player_participants.values().cloned().collect();
-    players_vec.sort_by(|a, b| b.score.cmp(&a.score));
+    // In a team game every player stands on their team's result, and teammates are not opponents.
+    // A battle royale is decided by elimination order: the later out, the better, with players
+    // still standing (first place) on top and players out in the same round drawing each other.
+    let teams: Vec<Option<LobbyTeam>> = players_vec.iter().map(|(p, _)| team_of(ctx, lobby_id, p.player_id)).collect();
+    let team_ids: Vec<Option<u64>> = teams.iter().map(|t| t.as_ref().map(|t| t.team_id)).collect();
+    let result_points: Vec<u32> = players_vec.iter().zip(&teams)
+        .map(|((p, points), team)| match (team, royale.get(&p.player_id)) {
+            (Some(team), _) => team.points,
+            (None, Some(standing)) => royale_result(standing.placement, royale.len()),
+            (None, None) => *points,
+        })
+        .collect();
 
-    for (i, player_to_update) in players_vec.iter().enumerate() {
-        let mut mutable_player = player_to_update.clone();
-        let opponent_elo_sum: i32 = players_vec.iter()
-            .filter(|p| p.player_id != mutable_player.player_id)
-            .map(|p| p.elo)
-            .sum();
-        let num_opponents = players_vec.len() - 1;
-        let average_opponent_elo = if num_opponents > 0 { opponent_elo_sum / num_opponents as i32 } else { mutable_player.elo };
+    // Pairwise settlement: every pair is a match decided by final result, ties are draws.
+    // Each player's K-factor depends on their experience and rating band.
+    let standings: Vec<(i32, u32)> = players_vec.iter().zip(&result_points).map(|((p, _), result)| (p.elo, *result)).collect();
+    let k_factors: Vec<f32> = players_vec.iter().map(|(p, _)| k_factor_for(p.games_played, p.elo)).collect();
+    let is_opponent = |i: usize, j: usize| are_opponents(&team_ids, i, j);
+    let elo_deltas = if lobby.game_mode == GAME_MODE_TEAMS {
+        team_elo_deltas(ctx, &players_vec, &team_ids)
+    } else {
+        calculate_multiplayer_elo_deltas_with_k(&standings, &k_factors)
+    };
 
-        let actual_score = if i == 0 { 1.0 }
-        else if i == 1 && players_vec.len() > 2 { 0.5 }
-        else { 0.0 };
+    // Glicko-2: the game is one rating period with a pairwise result against every opponent
+    let glicko_before: Vec<Glicko2Rating> = players_vec.iter()
+        .map(|(p, _)| ctx.db.player_glicko().player_id().find(p.player_id)
+            .unwrap_or_else(|| PlayerGlicko::new(p.player_id))
+            .rating_at(ctx.timestamp))
+        .collect();
+    for (i, (player, _)) in players_vec.iter().enumerate() {
+        let results: Vec<(Glicko2Rating, f64)> = (0..players_vec.len())
+            .filter(|j| is_opponent(i, *j))
+            .map(|j| (glicko_before[j], pairwise_outcome(result_points[i], result_points[j]) as f64))
+            .collect();
+        let updated = glicko2::update_rating(glicko_before[i], &results);
+        let row = PlayerGlicko {
+            player_id: player.player_id,
+            rating: updated.rating,
+            deviation: updated.deviation,
+            volatility: updated.volatility,
+            last_rated_at: Some(ctx.timestamp),
+        };
+        if ctx.db.player_glicko().player_id().find(player.player_id).is_some() {
+            ctx.db.player_glicko().player_id().update(row);
+        } else {
+            ctx.db.player_glicko().insert(row);
+        }
+    }
 
-        let elo_delta = calculate_elo_delta(mutable_player.elo, average_opponent_elo, actual_score, None);
-        mutable_player.elo += elo_delta;
-        mutable_player.score = 0;
+    // Per-topic ratings: each topic played is its own pairwise settlement among the settled players who answered it
+    let is_settled = |player_id: Identity| players_vec.iter().any(|(p, _)| p.player_id == player_id);
+    for (topic, scores) in topic_contests(&topic_answers, is_settled) {
+        let topic_ratings: Vec<(TopicRating, u32)> = scores.into_iter()
+            .map(|(player_id, score)| (topic_rating_for(ctx, player_id, &topic), score))
+            .collect();
+        let standings: Vec<(i32, u32)> = topic_ratings.iter().map(|(r, score)| (r.elo, *score)).collect();
+        let k_factors: Vec<f32> = topic_ratings.iter().map(|(r, _)| k_factor_for(r.games_played, r.elo)).collect();
+        let deltas = calculate_multiplayer_elo_deltas_with_k(&standings, &k_factors);
+
+        let topic_board = format!("{}{}", LEADERBOARD_TOPIC_PREFIX, topic);
+        for ((mut rating, _), delta) in topic_ratings.into_iter().zip(deltas) {
+            rating.elo += delta;
+            rating.games_played += 1;
+            if !is_provisional(rating.games_played) {
+                let name = players_vec.iter().find(|(p, _)| p.player_id == rating.player_id).map(|(p, _)| p.name.as_str()).unwrap_or_default();
+                upsert_leaderboard_entry(ctx, &topic_board, rating.player_id, name, rating.elo as i64);
+            }
+            if rating.topic_rating_id == 0 {
+                ctx.db.topic_rating().insert(rating);
+            } else {
+                ctx.db.topic_rating().topic_rating_id().update(rating);
+            }
+        }
+    }
 
-        if let Err(e) = ctx.db.player().try_insert(mutable_player.clone()) {
-            log::error!("Failed to update Elo/score for player {}: {}", mutable_player.player_id, e);
+    let opponents_average_elo = opponents_average_elo(&players_vec.iter().map(|(p, _)| p.elo).collect::<Vec<_>>(), &team_ids);
+    let weekly_board = weekly_leaderboard(ctx.timestamp);
+
+    for (((mut player, points), elo_delta), opponents_average_elo) in players_vec.into_iter().zip(elo_deltas).zip(opponents_average_elo) {
+        log::info!("Player {} finished with score {}: Elo {} -> {}", player.player_id, points, player.elo, player.elo + elo_delta);
+        ctx.db.rating_history().insert(RatingHistory {
+            history_id: 0,
+            player_id: player.player_id,
+            lobby_id,
+            elo_before: player.elo,
+            elo_after: player.elo + elo_delta,
+            delta: elo_delta,
+            opponents_average_elo,
+            recorded_at: ctx.timestamp,
+        });
+        let weekly_points = ctx.db.leaderboard_entry().board_player().filter((weekly_board.as_str(), player.player_id)).next()
+            .map(|e| e.value)
+            .unwrap_or(0);
+        upsert_leaderboard_entry(ctx, &weekly_board, player.player_id, &player.name, weekly_points + points as i64);
+
+        player.elo += elo_delta;
+        player.games_played += 1;
+        player.provisional = is_provisional(player.games_played);
+        ctx.db.player().player_id().update(player.clone());
+        refresh_rating_leaderboard(ctx, &player);
+    }
+    // Team ratings follow their members' new Elo
+    let mut settled_teams: Vec<u64> = team_ids.iter().flatten().copied().collect();
+    settled_teams.sort();
+    settled_teams.dedup();
+    for team_id in settled_teams {
+        if let Some(team) = ctx.db.lobby_team().team_id().find(team_id) {
+            refresh_team_rating(ctx, team);
         }
     }
 
+    clear_lobby_sessions(ctx, lobby_id);
     let mut final_lobby = lobby.clone();
     final_lobby.status = LOBBY_STATUS_FINISHED.to_string();
     final_lobby.next_round_is_lightning = false;
-    ctx.db.lobby().try_insert(final_lobby).map_err(|e| format!("Failed to set lobby {} to finished: {}", lobby_id, e))?;
+    ctx.db.lobby().lobby_id().update(final_lobby);
 
     log::info!("Finalized game and updated Elo for lobby {}", lobby_id);
     Ok(())
 }
 
+#[reducer]
+pub fn close_season(ctx: &ReducerContext, next_season_name: String, soft_reset_factor: f32) -> Result<(), String> {
+    if ctx.db.admin().admin_id().find(ctx.sender).is_none() {
+        return Err("Only an admin can close the season".to_string());
+    }
+
+    let next_season_name = next_season_name.trim().to_string();
+    if next_season_name.is_empty() {
+        return Err("Next season name cannot be empty".to_string());
+    }
+    if !(0.0..=1.0).contains(&soft_reset_factor) {
+        return Err(format!("Soft reset factor must be between 0 and 1 (got {})", soft_reset_factor));
+    }
+
+    let mut season = ctx.db.season().iter().find(|s| s.ended_at.is_none())
+        .ok_or("No season is currently open")?;
+
+    // Archive final standings, highest Elo first
+    let mut players: Vec<Player> = ctx.db.player().iter().collect();
+    players.sort_by_key(|p| std::cmp::Reverse(p.elo));
+    let ranks = competition_ranks(&players.iter().map(|p| p.elo).collect::<Vec<_>>());
+    for (player, rank) in players.iter().zip(ranks) {
+        ctx.db.season_standing().insert(SeasonStanding {
+            standing_id: 0,
+            season_id: season.season_id,
+            player_id: player.player_id,
+            player_name: player.name.clone(),
+            final_elo: player.elo,
+            rank,
+            games_played: player.games_played,
+            provisional: player.provisional,
+            score: player.season_score,
+        });
+    }
+
+    // Soft reset: pull every rating toward the starting rating and clear season points.
+    // Lifetime points (Player.score) carry over.
+    for mut player in players {
+        player.elo = soft_reset_elo(player.elo, soft_reset_factor);
+        player.season_score = 0;
+        ctx.db.player().player_id().update(player);
+    }
+    for mut glicko in ctx.db.player_glicko().iter().collect::<Vec<_>>() {
+        glicko.rating = glicko2::soft_reset_rating(glicko.rating, soft_reset_factor);
+        ctx.db.player_glicko().player_id().update(glicko);
+    }
+
+    // Topic ratings are seasonal too: each topic board is re-ranked after the reset
+    let mut topics: Vec<String> = Vec::new();
+    for mut rating in ctx.db.topic_rating().iter().collect::<Vec<_>>() {
+        rating.elo = soft_reset_elo(rating.elo, soft_reset_factor);
+        if !topics.contains(&rating.topic) {
+            topics.push(rating.topic.clone());
+        }
+        ctx.db.topic_rating().topic_rating_id().update(rating);
+    }
+
+    // Every rating moved, so re-rank the boards in one pass each
+    rebuild_rating_leaderboard(ctx);
+    for topic in topics {
+        rebuild_topic_leaderboard(ctx, &topic);
+    }
+
+    season.ended_at = Some(ctx.timestamp);
+    season.soft_reset_factor = Some(soft_reset_factor);
+    log::info!("Closed season {} ({}) with soft reset factor {}", season.season_id, season.name, soft_reset_factor);
+    ctx.db.season().season_id().update(season);
+
+    let next = ctx.db.season().insert(Season {
+        season_id: 0,
+        name: next_season_name,
+        started_at: ctx.timestamp,
+        ended_at: None,
+        soft_reset_factor: None,
+    });
+    log::info!("Opened season {} ({})", next.season_id, next.name);
+    Ok(())
+}
+
+#[reducer]
+pub fn set_player_name(ctx: &ReducerContext, name: String) -> Result<(), String> {
+    let mut player = ctx.db.player().player_id().find(ctx.sender)
+        .ok_or_else(|| "Player not found".to_string())?;
+    let name = validate_player_name(&name)?;
+    if name == player.name {
+        return Ok(());
+    }
+    if ctx.db.player().name().find(&name).is_some() {
+        return Err(format!("Player name '{}' is already taken", name));
+    }
+
+    log::info!("Player {} renamed from {} to {}", ctx.sender, player.name, name);
+    player.name = name.clone();
+    ctx.db.player().player_id().update(player);
+
+    // Leaderboards keep a copy of the name so clients can render them without joining
+    let entries: Vec<LeaderboardEntry> = ctx.db.leaderboard_entry().player_id().filter(ctx.sender).collect();
+    for mut entry in entries {
+        entry.player_name = name.clone();
+        ctx.db.leaderboard_entry().entry_id().update(entry);
+    }
+    Ok(())
+}
+
+#[reducer]
+pub fn set_avatar(ctx: &ReducerContext, avatar: String) -> Result<(), String> {
+    let mut player = ctx.db.player().player_id().find(ctx.sender)
+        .ok_or_else(|| "Player not found".to_string())?;
+    validate_avatar(&avatar)?;
+    player.avatar = avatar;
+    ctx.db.player().player_id().update(player);
+    Ok(())
+}
+
+/// Switches the rating that drives matchmaking and leaderboards, and rebuilds the all-time
+/// rating board from the newly active system.
+#[reducer]
+pub fn set_rating_system(ctx: &ReducerContext, rating_system: String) -> Result<(), String> {
+    if ctx.db.admin().admin_id().find(ctx.sender).is_none() {
+        return Err("Only an admin can change the rating system".to_string());
+    }
+    let rating_system = rating_system.trim().to_lowercase();
+    let systems = [RATING_SYSTEM_ELO, RATING_SYSTEM_GLICKO2];
+    if !systems.contains(&rating_system.as_str()) {
+        return Err(format!("Invalid rating system: {}. Expected one of: {}", rating_system, systems.join(", ")));
+    }
+    if active_rating_system(ctx) == rating_system {
+        return Err(format!("{} is already the active rating system", rating_system));
+    }
+
+    log::info!("Rating system switched to {} by {}", rating_system, ctx.sender);
+    let config = RatingConfig { config_id: RATING_CONFIG_ID, rating_system, updated_by: ctx.sender, updated_at: ctx.timestamp };
+    if ctx.db.rating_config().config_id().find(RATING_CONFIG_ID).is_some() {
+        ctx.db.rating_config().config_id().update(config);
+    } else {
+        ctx.db.rating_config().insert(config);
+    }
+    rebuild_rating_leaderboard(ctx);
+    Ok(())
+}
+
+/// Treats identities whose token comes from `issuer` as guests, e.g. the host's anonymous token issuer.
+#[reducer]
+pub fn add_guest_token_issuer(ctx: &ReducerContext, issuer: String) -> Result<(), String> {
+    if ctx.db.admin().admin_id().find(ctx.sender).is_none() {
+        return Err("Only an admin can configure guest token issuers".to_string());
+    }
+    let issuer = issuer.trim().to_string();
+    if issuer.is_empty() {
+        return Err("Issuer cannot be empty".to_string());
+    }
+    if ctx.db.guest_token_issuer().issuer().find(&issuer).is_some() {
+        return Err(format!("{} is already a guest token issuer", issuer));
+    }
+    log::info!("Tokens issued by {} now count as guests", issuer);
+    ctx.db.guest_token_issuer().insert(GuestTokenIssuer { issuer, added_by: ctx.sender, added_at: ctx.timestamp });
+    Ok(())
+}
+
+#[reducer]
+pub fn remove_guest_token_issuer(ctx: &ReducerContext, issuer: String) -> Result<(), String> {
+    if ctx.db.admin().admin_id().find(ctx.sender).is_none() {
+        return Err("Only an admin can configure guest token issuers".to_string());
+    }
+    if !ctx.db.guest_token_issuer().issuer().delete(issuer.trim().to_string()) {
+        return Err(format!("{} is not a guest token issuer", issuer.trim()));
+    }
+    log::info!("Tokens issued by {} no longer count as guests", issuer.trim());
+    Ok(())
+}
+
+/// Step one of a guest upgrade: the guest names the account that may claim their stats.
+#[reducer]
+pub fn request_account_link(ctx: &ReducerContext, account_id: Identity) -> Result<(), String> {
+    if !is_guest(ctx) {
+        return Err("Only guests can request an account link".to_string());
+    }
+    if account_id == ctx.sender {
+        return Err("Cannot link a guest to itself".to_string());
+    }
+    if ctx.db.player().player_id().find(ctx.sender).is_none() {
+        return Err("Player not found".to_string());
+    }
+
+    let request = AccountLinkRequest { guest_id: ctx.sender, account_id, requested_at: ctx.timestamp };
+    if ctx.db.account_link_request().guest_id().find(ctx.sender).is_some() {
+        ctx.db.account_link_request().guest_id().update(request);
+    } else {
+        ctx.db.account_link_request().insert(request);
+    }
+    log::info!("Guest {} requested to be merged into account {}", ctx.sender, account_id);
+    Ok(())
+}
+
+/// Step two of a guest upgrade: the signed-in account accepts the guest's request and takes over their stats.
+#[reducer]
+pub fn merge_guest_account(ctx: &ReducerContext, guest_id: Identity) -> Result<(), String> {
+    if is_guest(ctx) {
+        return Err("Sign in before merging a guest account".to_string());
+    }
+    let request = ctx.db.account_link_request().guest_id().find(guest_id)
+        .filter(|r| r.account_id == ctx.sender)
+        .ok_or_else(|| format!("Guest {} has not requested a link to this account", guest_id))?;
+    if ctx.timestamp.to_micros_since_unix_epoch() - request.requested_at.to_micros_since_unix_epoch() > ACCOUNT_LINK_TTL_MICROS {
+        ctx.db.account_link_request().guest_id().delete(guest_id);
+        return Err("Account link request has expired".to_string());
+    }
+    if in_unfinished_lobby(ctx, guest_id) || in_unfinished_lobby(ctx, ctx.sender) {
+        return Err("Cannot merge accounts while either one is in an unfinished lobby".to_string());
+    }
+    let guest = ctx.db.player().player_id().find(guest_id)
+        .ok_or_else(|| "Guest player not found".to_string())?;
+    let account = ctx.db.player().player_id().find(ctx.sender);
+
+    log::info!("Merging guest {} ({}) into account {}", guest_id, guest.name, ctx.sender);
+    ctx.db.account_link_request().guest_id().delete(guest_id);
+    merge_guest_into_account(ctx, guest, account);
+    Ok(())
+}
+
+#[reducer]
+pub fn grant_moderator(ctx: &ReducerContext, player_id: Identity, reason: String) -> Result<(), String> {
+    if ctx.db.admin().admin_id().find(ctx.sender).is_none() {
+        return Err("Only an admin can grant moderator".to_string());
+    }
+    let reason = validate_reason(&reason)?;
+    if ctx.db.moderator().moderator_id().find(player_id).is_some() {
+        return Err(format!("{} is already a moderator", player_id));
+    }
+    ctx.db.moderator().insert(Moderator { moderator_id: player_id, granted_by: ctx.sender, granted_at: ctx.timestamp });
+    record_moderation(ctx, player_id, MODERATION_ACTION_GRANT_MODERATOR, &reason, None, None);
+    Ok(())
+}
+
+#[reducer]
+pub fn revoke_moderator(ctx: &ReducerContext, player_id: Identity, reason: String) -> Result<(), String> {
+    if ctx.db.admin().admin_id().find(ctx.sender).is_none() {
+        return Err("Only an admin can revoke moderator".to_string());
+    }
+    let reason = validate_reason(&reason)?;
+    if !ctx.db.moderator().moderator_id().delete(player_id) {
+        return Err(format!("{} is not a moderator", player_id));
+    }
+    record_moderation(ctx, player_id, MODERATION_ACTION_REVOKE_MODERATOR, &reason, None, None);
+    Ok(())
+}
+
+#[reducer]
+pub fn kick_player(ctx: &ReducerContext, lobby_id: u64, player_id: Identity, reason: String) -> Result<(), String> {
+    let lobby = ctx.db.lobby().lobby_id().find(lobby_id)
+        .ok_or_else(|| format!("Lobby {} not found", lobby_id))?;
+    authorize_moderation(ctx, player_id, Some(lobby_id))?;
+    let reason = validate_reason(&reason)?;
+    if !lobby_is_open(&lobby) {
+        return Err(format!("Lobby {} is {}", lobby_id, lobby.status));
+    }
+    if !ctx.db.lobby_member().lobby_id().filter(lobby_id).any(|m| m.player_id == player_id) {
+        return Err(format!("Player {} is not in lobby {}", player_id, lobby_id));
+    }
+
+    remove_lobby_member(ctx, lobby_id, player_id);
+    record_moderation(ctx, player_id, MODERATION_ACTION_KICK, &reason, Some(lobby_id), None);
+    Ok(())
+}
+
+/// Mutes a player in one lobby (host or moderator) or everywhere (`lobby_id` None, moderators only).
+#[reducer]
+pub fn mute_player(
+    ctx: &ReducerContext,
+    player_id: Identity,
+    lobby_id: Option<u64>,
+    duration_secs: Option<u64>,
+    reason: String,
+) -> Result<(), String> {
+    authorize_moderation(ctx, player_id, lobby_id)?;
+    let reason = validate_reason(&reason)?;
+    if ctx.db.player().player_id().find(player_id).is_none() {
+        return Err("Player not found".to_string());
+    }
+
+    let expires_at = expiry_after(ctx, duration_secs)?;
+    ctx.db.player_mute().insert(PlayerMute {
+        mute_id: 0,
+        player_id,
+        lobby_id,
+        muted_by: ctx.sender,
+        muted_at: ctx.timestamp,
+        expires_at,
+    });
+    record_moderation(ctx, player_id, MODERATION_ACTION_MUTE, &reason, lobby_id, expires_at);
+    Ok(())
+}
+
+/// Lifts a player's mutes in one lobby (host or moderator) or their global mutes (`lobby_id` None, moderators only).
+#[reducer]
+pub fn unmute_player(ctx: &ReducerContext, player_id: Identity, lobby_id: Option<u64>, reason: String) -> Result<(), String> {
+    authorize_moderation(ctx, player_id, lobby_id)?;
+    let reason = validate_reason(&reason)?;
+
+    let mutes: Vec<u64> = ctx.db.player_mute().player_id().filter(player_id)
+        .filter(|mute| mute.lobby_id == lobby_id)
+        .map(|mute| mute.mute_id)
+        .collect();
+    if mutes.is_empty() {
+        return Err(format!("Player {} is not muted there", player_id));
+    }
+    for mute_id in mutes {
+        ctx.db.player_mute().mute_id().delete(mute_id);
+    }
+    record_moderation(ctx, player_id, MODERATION_ACTION_UNMUTE, &reason, lobby_id, None);
+    Ok(())
+}
+
+/// Bans a player from joining lobbies and removes them from any they are in. Moderators only.
+#[reducer]
+pub fn ban_player(ctx: &ReducerContext, player_id: Identity, duration_secs: Option<u64>, reason: String) -> Result<(), String> {
+    authorize_moderation(ctx, player_id, None)?;
+    let reason = validate_reason(&reason)?;
+
+    let expires_at = expiry_after(ctx, duration_secs)?;
+    let ban = PlayerBan { player_id, banned_by: ctx.sender, reason: reason.clone(), banned_at: ctx.timestamp, expires_at };
+    if ctx.db.player_ban().player_id().find(player_id).is_some() {
+        ctx.db.player_ban().player_id().update(ban);
+    } else {
+        ctx.db.player_ban().insert(ban);
+    }
+
+    let open_lobbies: Vec<u64> = ctx.db.lobby_member().player_id().filter(player_id)
+        .map(|m| m.lobby_id)
+        .filter(|id| ctx.db.lobby().lobby_id().find(id).is_some_and(|l| lobby_is_open(&l)))
+        .collect();
+    for lobby_id in open_lobbies {
+        remove_lobby_member(ctx, lobby_id, player_id);
+    }
+    record_moderation(ctx, player_id, MODERATION_ACTION_BAN, &reason, None, expires_at);
+    Ok(())
+}
+
+#[reducer]
+pub fn unban_player(ctx: &ReducerContext, player_id: Identity, reason: String) -> Result<(), String> {
+    authorize_moderation(ctx, player_id, None)?;
+    let reason = validate_reason(&reason)?;
+    if !ctx.db.player_ban().player_id().delete(player_id) {
+        return Err(format!("Player {} is not banned", player_id));
+    }
+    record_moderation(ctx, player_id, MODERATION_ACTION_UNBAN, &reason, None, None);
+    Ok(())
+}
+
+#[reducer]
+pub fn send_chat(ctx: &ReducerContext, lobby_id: u64, text: String) -> Result<(), String> {
+    let lobby = ctx.db.lobby().lobby_id().find(lobby_id)
+        .ok_or_else(|| format!("Lobby {} not found", lobby_id))?;
+    if !lobby_is_open(&lobby) {
+        return Err(format!("Lobby {} is {}", lobby_id, lobby.status));
+    }
+    if !ctx.db.lobby_member().lobby_id().filter(lobby_id).any(|m| m.player_id == ctx.sender) {
+        return Err(format!("Not a member of lobby {}", lobby_id));
+    }
+    let player = ctx.db.player().player_id().find(ctx.sender)
+        .ok_or_else(|| "Player not found".to_string())?;
+    if is_muted(ctx, ctx.sender, lobby_id) {
+        return Err("You are muted".to_string());
+    }
+
+    let text = prepare_message(&text)?;
+
+    let existing = ctx.db.chat_rate_limit().player_id().find(ctx.sender);
+    let current = existing.as_ref().map(|w| RateWindow {
+        started_at_micros: w.window_started_at.to_micros_since_unix_epoch(),
+        messages: w.messages_in_window,
+    });
+    let counted = count_message(current, ctx.timestamp.to_micros_since_unix_epoch())?;
+    let window = ChatRateLimit {
+        player_id: ctx.sender,
+        window_started_at: Timestamp::from_micros_since_unix_epoch(counted.started_at_micros),
+        messages_in_window: counted.messages,
+    };
+    if existing.is_some() {
+        ctx.db.chat_rate_limit().player_id().update(window);
+    } else {
+        ctx.db.chat_rate_limit().insert(window);
+    }
+
+    ctx.db.chat_message().insert(ChatMessage {
+        message_id: 0,
+        lobby_id,
+        sender_id: ctx.sender,
+        sender_name: player.name,
+        text,
+        sent_at: ctx.timestamp,
+        sent_at_micros: ctx.timestamp.to_micros_since_unix_epoch(),
+    });
+    Ok(())
+}
+
+#[reducer]
+pub fn purge_old_chat(ctx: &ReducerContext, _schedule: ChatPurgeSchedule) -> Result<(), String> {
+    if ctx.sender != ctx.identity() {
+        return Err("purge_old_chat may only be invoked by the scheduler".to_string());
+    }
+    let cutoff = retention_cutoff_micros(ctx.timestamp.to_micros_since_unix_epoch());
+    let purged = ctx.db.chat_message().sent_at_micros().delete(..cutoff);
+    if purged > 0 {
+        log::info!("Purged {} chat messages older than the retention window", purged);
+    }
+    Ok(())
+}
+
+/// Writes highlights for every round scored since the last run.
+#[reducer]
+pub fn detect_highlights(ctx: &ReducerContext, _schedule: HighlightSchedule) -> Result<(), String> {
+    if ctx.sender != ctx.identity() {
+        return Err("detect_highlights may only be invoked by the scheduler".to_string());
+    }
+    let pending: Vec<PendingHighlightScan> = ctx.db.pending_highlight_scan().iter().collect();
+    for scan in pending {
+        ctx.db.pending_highlight_scan().round_id().delete(scan.round_id);
+        let Some(round) = ctx.db.active_round().round_id().find(scan.round_id) else {
+            continue;
+        };
+        for highlight in find_round_highlights(ctx, &round, scan.scored_at) {
+            log::info!("Highlight {} in lobby {} round {}", highlight.kind, highlight.lobby_id, highlight.round_id);
+            ctx.db.highlight().insert(highlight);
+        }
+    }
+    Ok(())
+}
+
 #[reducer]
 pub fn request_agent_work(ctx: &ReducerContext, agent_id: u64, topic_json_payload: String) -> Result<(), String> {
     log::info!(
@@ -748,6 +3432,7 @@ pub fn submit_generated_questions(
             continue; // Skip this question and try the next
         }
 
+        let initial_rating = item_rating_for_label(&new_q_data.difficulty);
         let question_to_insert = Question {
             question_id: 0, // Auto-incremented
             text: new_q_data.text,
@@ -757,6 +3442,8 @@ pub fn submit_generated_questions(
             difficulty: new_q_data.difficulty,
             quality_score: 0, // Default quality score for new questions
             origin_agent: Some(agent_id.to_string()), // Tag with the generating agent's ID
+            rating: initial_rating, // Seeded from the agent's difficulty label until calibrated
+            rated_answers: 0,
         };
 
         if ctx.db.question_bank().try_insert(question_to_insert).is_ok() {
@@ -791,7 +3478,7 @@ pub fn update_agent_job_status(
     // Optional: Add permission check here to ensure only authorized workers/agents can update job statuses.
     // For example, check if ctx.sender is a registered agent or a specific worker identity.
 
-    let mut job = ctx.db.agent_job_queue().job_id().find(&job_id)
+    let mut job = ctx.db.agent_job_queue().job_id().find(job_id)
         .ok_or_else(|| format!("Agent job_id: {} not found for status update.", job_id))?;
 
     // Validate new_status against known statuses if desired
@@ -806,17 +3493,9 @@ pub fn update_agent_job_status(
     job.error_message = error_details;
     // job.updated_at = Some(ctx.timestamp); // If using timestamps
 
-    match ctx.db.agent_job_queue().try_insert(job.clone()) {
-        Ok(_) => {
-            log::info!("Successfully updated status for agent job_id: {}", job_id);
-            Ok(())
-        }
-        Err(e) => {
-            let err_msg = format!("Failed to update status for agent job_id {}: {}", job_id, e);
-            log::error!("{}", err_msg);
-            Err(err_msg)
-        }
-    }
+    ctx.db.agent_job_queue().job_id().update(job);
+    log::info!("Successfully updated status for agent job_id: {}", job_id);
+    Ok(())
 }
 
 #[cfg(test)]
@@ -852,6 +3531,10 @@ mod tests {
         assert!(player1.name.starts_with("Player_")); // Default name generation
         assert_eq!(player1.score, 0);
         assert_eq!(player1.elo, 1200); // Check default Elo
+        assert_eq!(player1.games_played, 0);
+        assert!(player1.provisional, "New players start with a provisional rating");
+        assert_eq!(player1.avatar, DEFAULT_AVATAR);
+        assert!(player1.online);
 
         // Verify Lobby table
         let lobbies = Lobby::iter(&db).collect::<Vec<_>>();
@@ -1010,6 +3693,23 @@ mod tests {
         (lobby.lobby_id, active_round.round_id)
     }
 
+    // Puts players on the lobby's scoreboard as if they had earned these points in its rounds
+    fn set_lobby_points(db: &mut SpacetimeDb, lobby_id: u64, points: &[(Identity, u32)]) {
+        for (player_id, points) in points {
+            LobbyScore::insert(db, LobbyScore {
+                score_id: 0,
+                lobby_id,
+                player_id: *player_id,
+                player_name: String::new(),
+                points: *points,
+                correct_count: 0,
+                streak: 0,
+                rank: 0,
+                updated_at: Timestamp::from_micros_since_unix_epoch(0),
+            }).unwrap();
+        }
+    }
+
     #[spacetimedb(test)]
     fn test_submit_answer_success(mut db: SpacetimeDb) {
         let (_lobby_id, round_id) = setup_game_for_round_tests(&mut db);
@@ -1251,21 +3951,15 @@ mod tests {
         db.call_reducer(BOT_1_IDENTITY, "start_game", (lobby_id,)).expect("Start game failed");
         let round_id = ActiveRound::iter(&db).next().unwrap().round_id;
         // Bot 2 answers (to be found as participant)
-        Answer::insert(&mut db, Answer { answer_id: 0, round_id, player_id: BOT_2_IDENTITY, chosen_answer_index: 0, score: Some(10) }).unwrap();
+        Answer::insert(&mut db, Answer { answer_id: 0, round_id, player_id: BOT_2_IDENTITY, chosen_answer_index: 0, score: Some(10), submitted_at: Timestamp::from_micros_since_unix_epoch(0) }).unwrap();
         // Bot 3 answers (to be found as participant)
-        Answer::insert(&mut db, Answer { answer_id: 0, round_id, player_id: BOT_3_IDENTITY, chosen_answer_index: 1, score: Some(5) }).unwrap();
-
-        // Manually update player scores for ranking
-        let mut player1 = Player::filter_by_player_id(&db, BOT_1_IDENTITY).unwrap(); // Host also plays
-        player1.score = 10;
-        Player::update_by_player_id(&mut db, BOT_1_IDENTITY, player1);
-
-        let mut player2 = Player::filter_by_player_id(&db, BOT_2_IDENTITY).unwrap();
-        player2.score = 20; // Bot 2 wins
-        Player::update_by_player_id(&mut db, BOT_2_IDENTITY, player2);
+        Answer::insert(&mut db, Answer { answer_id: 0, round_id, player_id: BOT_3_IDENTITY, chosen_answer_index: 1, score: Some(5), submitted_at: Timestamp::from_micros_since_unix_epoch(0) }).unwrap();
 
+        // Lobby scores for ranking: the host also plays, Bot 2 wins, Bot 3 loses.
+        // Lifetime points from earlier games must not affect the ranking.
+        set_lobby_points(&mut db, lobby_id, &[(BOT_1_IDENTITY, 10), (BOT_2_IDENTITY, 20), (BOT_3_IDENTITY, 5)]);
         let mut player3 = Player::filter_by_player_id(&db, BOT_3_IDENTITY).unwrap();
-        player3.score = 5; // Bot 3 loses
+        player3.score = 500;
         Player::update_by_player_id(&mut db, BOT_3_IDENTITY, player3);
 
         // Bot 1 (host) finalizes the game
@@ -1273,20 +3967,19 @@ mod tests {
         assert!(result.is_ok(), "finalize_game_and_update_elo failed: {:?}", result.err());
 
         // Verify Elos updated (initial Elo is 1200 for all)
-        // Bot 2 (winner, score 20) vs avg_elo_others ( (1200(B1)+1200(B3))/2 = 1200). actual_score = 1.0. Delta = 24 * (1-0.5) = 12
+        // Pairwise settlement, all at 1200 and provisional (K = 40), split over 2 opponents (20 per pair).
+        // Bot 2 (winner, score 20) beats B1 and B3. Delta = 20 * (1-0.5) + 20 * (1-0.5) = 20
         let p2_final = Player::filter_by_player_id(&db, BOT_2_IDENTITY).unwrap();
-        assert_eq!(p2_final.elo, 1200 + 12, "Bot 2 Elo mismatch");
-        assert_eq!(p2_final.score, 0, "Bot 2 score should be reset");
+        assert_eq!(p2_final.elo, 1200 + 20, "Bot 2 Elo mismatch");
 
-        // Bot 1 (2nd place, score 10) vs avg_elo_others ( (1200(B2)+1200(B3))/2 = 1200). actual_score = 0.5. Delta = 24 * (0.5-0.5) = 0
+        // Bot 1 (2nd place, score 10) loses to B2, beats B3. Delta = 20 * (0-0.5) + 20 * (1-0.5) = 0
         let p1_final = Player::filter_by_player_id(&db, BOT_1_IDENTITY).unwrap();
         assert_eq!(p1_final.elo, 1200 + 0, "Bot 1 Elo mismatch");
-        assert_eq!(p1_final.score, 0, "Bot 1 score should be reset");
 
-        // Bot 3 (3rd place, score 5) vs avg_elo_others ( (1200(B1)+1200(B2))/2 = 1200). actual_score = 0.0. Delta = 24 * (0-0.5) = -12
+        // Bot 3 (3rd place, score 5) loses to B1 and B2. Delta = 20 * (0-0.5) + 20 * (0-0.5) = -20
         let p3_final = Player::filter_by_player_id(&db, BOT_3_IDENTITY).unwrap();
-        assert_eq!(p3_final.elo, 1200 - 12, "Bot 3 Elo mismatch");
-        assert_eq!(p3_final.score, 0, "Bot 3 score should be reset");
+        assert_eq!(p3_final.elo, 1200 - 20, "Bot 3 Elo mismatch");
+        assert_eq!(p3_final.score, 500, "Lifetime points are kept after the game");
 
         // Verify Lobby status
         let final_lobby = Lobby::filter_by_lobby_id(&db, lobby_id).unwrap();
@@ -1469,6 +4162,8 @@ mod tests {
         assert_eq!(submitted_q1.topic, "Math");
         assert_eq!(submitted_q1.quality_score, 0);
         assert_eq!(submitted_q1.origin_agent, Some(agent_id.to_string()));
+        assert_eq!(submitted_q1.rating, elo::item_rating_for_label("Easy"), "Item rating should be seeded from the label");
+        assert_eq!(submitted_q1.rated_answers, 0);
     }
 
     #[spacetimedb(test)]
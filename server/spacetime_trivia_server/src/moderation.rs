@@ -0,0 +1,153 @@
+const MODERATION_REASON_MAX_LENGTH: usize = 200;
+
+/// Who is acting on whom, as moderation permissions see it.
+pub struct ModerationRequest {
+    pub is_self: bool,
+    pub actor_is_admin: bool,
+    pub actor_is_moderator: bool, // Admins count as moderators
+    pub target_is_moderator: bool,
+    pub actor_hosts_lobby: Option<bool>, // None for a global action, or when the lobby does not exist
+}
+
+/// Checks that the actor may act on the target: moderators anywhere, or the host inside their own
+/// lobby. Moderators can only be acted on by admins.
+pub fn authorize(request: &ModerationRequest) -> Result<(), String> {
+    if request.is_self {
+        return Err("Cannot moderate yourself".to_string());
+    }
+    if request.target_is_moderator && !request.actor_is_admin {
+        return Err("Only an admin can moderate a moderator".to_string());
+    }
+    if request.actor_is_moderator {
+        return Ok(());
+    }
+    match request.actor_hosts_lobby {
+        Some(true) => Ok(()),
+        Some(false) => Err("Only the host or a moderator can moderate this lobby".to_string()),
+        None => Err("Only a moderator can take global moderation actions".to_string()),
+    }
+}
+
+/// Trims the reason and enforces the length limit.
+pub fn validate_reason(reason: &str) -> Result<String, String> {
+    let reason = reason.trim();
+    if reason.chars().count() > MODERATION_REASON_MAX_LENGTH {
+        return Err(format!("Reason must be at most {} characters", MODERATION_REASON_MAX_LENGTH));
+    }
+    Ok(reason.to_string())
+}
+
+/// Expiry `duration_secs` after `now_micros`, or None for an open-ended sanction.
+/// Fails for durations that do not fit in a timestamp; use None for a permanent sanction instead.
+pub fn expiry_micros_after(now_micros: i64, duration_secs: Option<u64>) -> Result<Option<i64>, String> {
+    let Some(secs) = duration_secs else {
+        return Ok(None);
+    };
+    secs.checked_mul(1_000_000)
+        .and_then(|micros| i64::try_from(micros).ok())
+        .and_then(|micros| now_micros.checked_add(micros))
+        .map(Some)
+        .ok_or_else(|| format!("Duration of {} seconds is too long; omit it for an open-ended sanction", secs))
+}
+
+/// True while a sanction expiring at `expires_at_micros` (None: never) is in force.
+pub fn is_in_force(expires_at_micros: Option<i64>, now_micros: i64) -> bool {
+    expires_at_micros.is_none_or(|expires_at| expires_at > now_micros)
+}
+
+/// True if a mute scoped to `mute_lobby` (None: everywhere) silences the player in `lobby_id`.
+pub fn mute_covers<L: PartialEq>(mute_lobby: Option<L>, lobby_id: L) -> bool {
+    mute_lobby.is_none_or(|muted_lobby| muted_lobby == lobby_id)
+}
+
+/// Whether a merging guest's ban, expiring at `guest_ban` (None: never), replaces the account's.
+/// `account_ban` is the expiry of the account's ban row, if it has one. Only a ban in force
+/// carries over, and only when the account has no ban in force that lasts as long.
+pub fn guest_ban_prevails(account_ban: Option<Option<i64>>, guest_ban: Option<i64>, now_micros: i64) -> bool {
+    if !is_in_force(guest_ban, now_micros) {
+        return false;
+    }
+    match account_ban.filter(|&expires_at| is_in_force(expires_at, now_micros)) {
+        None => true,
+        Some(None) => false,
+        Some(Some(account_expires_at)) => guest_ban.is_none_or(|guest_expires_at| guest_expires_at > account_expires_at),
+    }
+}
+
+/// The mutes among `(mute_id, expires_at_micros)` still in force; a merging guest hands these on
+/// to the account and the rest are dropped.
+pub fn mutes_in_force<M: Copy>(mutes: &[(M, Option<i64>)], now_micros: i64) -> Vec<M> {
+    mutes.iter().filter(|(_, expires_at)| is_in_force(*expires_at, now_micros)).map(|(mute_id, _)| *mute_id).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(actor_is_admin: bool, actor_is_moderator: bool, target_is_moderator: bool, actor_hosts_lobby: Option<bool>) -> ModerationRequest {
+        ModerationRequest { is_self: false, actor_is_admin, actor_is_moderator, target_is_moderator, actor_hosts_lobby }
+    }
+
+    #[test]
+    fn test_authorize_hosts_in_their_lobby_and_moderators_anywhere() {
+        assert!(authorize(&request(false, false, false, Some(true))).is_ok(), "Hosts can kick and mute in their lobby");
+        assert!(authorize(&request(false, false, false, Some(false))).unwrap_err().contains("Only the host or a moderator"));
+        assert!(authorize(&request(false, false, false, None)).unwrap_err().contains("Only a moderator"), "Hosts cannot ban");
+        assert!(authorize(&request(false, true, false, None)).is_ok());
+        assert!(authorize(&request(false, true, false, Some(false))).is_ok());
+    }
+
+    #[test]
+    fn test_authorize_protects_moderators_and_self() {
+        assert!(authorize(&request(false, false, true, Some(true))).unwrap_err().contains("Only an admin can moderate a moderator"));
+        assert!(authorize(&request(false, true, true, None)).unwrap_err().contains("Only an admin"));
+        assert!(authorize(&request(true, true, true, None)).is_ok());
+        let own = ModerationRequest { is_self: true, ..request(true, true, false, None) };
+        assert!(authorize(&own).unwrap_err().contains("Cannot moderate yourself"));
+    }
+
+    #[test]
+    fn test_validate_reason_trims_and_limits_length() {
+        assert_eq!(validate_reason("  cheating ").unwrap(), "cheating");
+        assert!(validate_reason(&"a".repeat(MODERATION_REASON_MAX_LENGTH)).is_ok());
+        assert!(validate_reason(&"a".repeat(MODERATION_REASON_MAX_LENGTH + 1)).unwrap_err().contains("at most"));
+    }
+
+    #[test]
+    fn test_expiry_micros_after_rejects_overflow() {
+        assert_eq!(expiry_micros_after(5_000_000, Some(3600)), Ok(Some(3_605_000_000)));
+        assert_eq!(expiry_micros_after(5_000_000, None), Ok(None));
+        assert!(expiry_micros_after(5_000_000, Some(u64::MAX)).unwrap_err().contains("too long"), "Overflowing durations are rejected, not wrapped");
+        assert!(expiry_micros_after(i64::MAX - 1, Some(1)).is_err());
+    }
+
+    #[test]
+    fn test_is_in_force_until_expiry() {
+        assert!(is_in_force(None, i64::MAX), "Open-ended sanctions never lapse");
+        assert!(is_in_force(Some(3_600), 3_599));
+        assert!(!is_in_force(Some(3_600), 3_600));
+    }
+
+    #[test]
+    fn test_mute_covers_its_lobby_or_everywhere() {
+        assert!(mute_covers(Some(7), 7));
+        assert!(!mute_covers(Some(7), 8));
+        assert!(mute_covers(None, 8));
+    }
+
+    #[test]
+    fn test_guest_ban_prevails_only_when_in_force_and_longer() {
+        assert!(guest_ban_prevails(None, None, 100), "A permanent guest ban carries over to an unbanned account");
+        assert!(guest_ban_prevails(None, Some(200), 100));
+        assert!(!guest_ban_prevails(None, Some(50), 100), "An expired guest ban is dropped");
+        assert!(guest_ban_prevails(Some(Some(150)), Some(200), 100), "The longer ban wins");
+        assert!(!guest_ban_prevails(Some(Some(250)), Some(200), 100));
+        assert!(!guest_ban_prevails(Some(None), None, 100), "The account's permanent ban already covers it");
+        assert!(guest_ban_prevails(Some(Some(50)), Some(200), 100), "An expired account ban row is replaced");
+    }
+
+    #[test]
+    fn test_mutes_in_force_drops_expired_mutes() {
+        assert_eq!(mutes_in_force(&[(1, None), (2, Some(50)), (3, Some(200))], 100), vec![1, 3]);
+    }
+}
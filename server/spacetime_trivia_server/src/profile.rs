@@ -0,0 +1,62 @@
+use crate::word_filter::contains_blocked_word;
+
+const PLAYER_NAME_MIN_LENGTH: usize = 3;
+const PLAYER_NAME_MAX_LENGTH: usize = 20;
+const GUEST_NAME_PREFIX: &str = "Player_"; // Reserved for auto-generated guest names
+pub const AVATARS: &[&str] = &["owl", "fox", "cat", "panda", "robot", "astronaut", "wizard", "dragon"];
+pub const DEFAULT_AVATAR: &str = "owl";
+
+/// Checks a requested display name and returns it trimmed.
+pub fn validate_player_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    let length = name.chars().count();
+    if !(PLAYER_NAME_MIN_LENGTH..=PLAYER_NAME_MAX_LENGTH).contains(&length) {
+        return Err(format!("Player name must be between {} and {} characters", PLAYER_NAME_MIN_LENGTH, PLAYER_NAME_MAX_LENGTH));
+    }
+    if !name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == ' ') {
+        return Err("Player name may only contain letters, digits, spaces, '_' and '-'".to_string());
+    }
+    if name.starts_with(GUEST_NAME_PREFIX) {
+        return Err(format!("Player names starting with '{}' are reserved for guests", GUEST_NAME_PREFIX));
+    }
+    if contains_blocked_word(name) {
+        return Err("Player name contains a blocked word".to_string());
+    }
+    Ok(name.to_string())
+}
+
+/// Checks that `avatar` is one of `AVATARS`.
+pub fn validate_avatar(avatar: &str) -> Result<(), String> {
+    if !AVATARS.contains(&avatar) {
+        return Err(format!("Unknown avatar '{}'. Expected one of: {}", avatar, AVATARS.join(", ")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_player_name_trims() {
+        assert_eq!(validate_player_name("  Quiz Wizard ").unwrap(), "Quiz Wizard");
+        assert_eq!(validate_player_name("Trivia_Ace").unwrap(), "Trivia_Ace");
+    }
+
+    #[test]
+    fn test_validate_player_name_rejects_invalid_names() {
+        assert!(validate_player_name("Al").unwrap_err().contains("between"));
+        assert!(validate_player_name("   Al   ").unwrap_err().contains("between"), "Length is checked after trimming");
+        assert!(validate_player_name("A name far too long to fit").unwrap_err().contains("between"));
+        assert!(validate_player_name("semi;colon").unwrap_err().contains("may only contain"));
+        assert!(validate_player_name("Player_deadbeef").unwrap_err().contains("reserved for guests"));
+        assert!(validate_player_name("sh1t_happens").unwrap_err().contains("blocked word"));
+    }
+
+    #[test]
+    fn test_validate_avatar() {
+        assert!(validate_avatar("robot").is_ok());
+        assert!(validate_avatar(DEFAULT_AVATAR).is_ok());
+        assert!(validate_avatar("unicorn").unwrap_err().contains("Unknown avatar"));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::elo::{DIFFICULTY_EASY, DIFFICULTY_HARD, DIFFICULTY_MEDIUM};
+
+/// What round selection needs to know about a question.
+pub trait Candidate {
+    fn question_id(&self) -> u64;
+    fn rating(&self) -> i32;
+    fn difficulty(&self) -> &str;
+}
+
+/// Canonical band for a difficulty name typed by a host (case-insensitive, surrounding spaces ignored).
+pub fn parse_difficulty(name: &str) -> Option<&'static str> {
+    [DIFFICULTY_EASY, DIFFICULTY_MEDIUM, DIFFICULTY_HARD].into_iter()
+        .find(|band| band.eq_ignore_ascii_case(name.trim()))
+}
+
+/// Drops questions already played in the lobby, unless that would leave nothing to play.
+pub fn skip_played<Q: Candidate>(candidates: &mut Vec<Q>, played: &[u64]) {
+    if candidates.iter().any(|q| !played.contains(&q.question_id())) {
+        candidates.retain(|q| !played.contains(&q.question_id()));
+    }
+}
+
+/// Adaptive mode: the questions rated within `low..=high`, or the single question closest to
+/// the middle of that band when none are.
+pub fn adaptive_choices<Q: Candidate>(candidates: Vec<Q>, low: i32, high: i32) -> Vec<Q> {
+    if candidates.iter().any(|q| (low..=high).contains(&q.rating())) {
+        return candidates.into_iter().filter(|q| (low..=high).contains(&q.rating())).collect();
+    }
+    let target = (low + high) / 2;
+    candidates.into_iter().min_by_key(|q| (q.rating() - target).abs()).into_iter().collect()
+}
+
+/// Fixed mode: only the questions in `difficulty`.
+pub fn fixed_choices<Q: Candidate>(candidates: Vec<Q>, difficulty: &str) -> Vec<Q> {
+    candidates.into_iter().filter(|q| q.difficulty().eq_ignore_ascii_case(difficulty)).collect()
+}
+
+/// Mixed mode: rotates through Easy, Medium and Hard by round number, falling back to anything
+/// available when the round's band has no questions left.
+pub fn mixed_choices<Q: Candidate>(mut candidates: Vec<Q>, rounds_played: usize) -> Vec<Q> {
+    let band = [DIFFICULTY_EASY, DIFFICULTY_MEDIUM, DIFFICULTY_HARD][rounds_played % 3];
+    if candidates.iter().any(|q| q.difficulty().eq_ignore_ascii_case(band)) {
+        candidates.retain(|q| q.difficulty().eq_ignore_ascii_case(band));
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct TestQuestion(u64, i32, &'static str);
+
+    impl Candidate for TestQuestion {
+        fn question_id(&self) -> u64 { self.0 }
+        fn rating(&self) -> i32 { self.1 }
+        fn difficulty(&self) -> &str { self.2 }
+    }
+
+    fn ids(questions: &[TestQuestion]) -> Vec<u64> {
+        questions.iter().map(|q| q.0).collect()
+    }
+
+    fn bank() -> Vec<TestQuestion> {
+        vec![TestQuestion(1, 1000, "Easy"), TestQuestion(2, 1200, "Medium"), TestQuestion(3, 1400, "Hard"), TestQuestion(4, 2400, "Hard")]
+    }
+
+    #[test]
+    fn test_parse_difficulty() {
+        assert_eq!(parse_difficulty(" hard "), Some(DIFFICULTY_HARD));
+        assert_eq!(parse_difficulty("EASY"), Some(DIFFICULTY_EASY));
+        assert_eq!(parse_difficulty("chaotic"), None);
+    }
+
+    #[test]
+    fn test_skip_played_keeps_bank_when_everything_was_played() {
+        let mut candidates = bank();
+        skip_played(&mut candidates, &[1, 3]);
+        assert_eq!(ids(&candidates), vec![2, 4]);
+
+        let mut candidates = bank();
+        skip_played(&mut candidates, &[1, 2, 3, 4]);
+        assert_eq!(ids(&candidates), vec![1, 2, 3, 4], "An exhausted bank is replayed rather than left empty");
+    }
+
+    #[test]
+    fn test_adaptive_choices_prefers_in_band_then_closest() {
+        assert_eq!(ids(&adaptive_choices(bank(), 1100, 1300)), vec![2]);
+        assert_eq!(ids(&adaptive_choices(bank(), 1900, 2000)), vec![4], "Nothing in band: closest to the middle");
+        assert!(adaptive_choices(Vec::<TestQuestion>::new(), 1100, 1300).is_empty());
+    }
+
+    #[test]
+    fn test_fixed_choices_filters_by_band() {
+        assert_eq!(ids(&fixed_choices(bank(), DIFFICULTY_HARD)), vec![3, 4]);
+        assert!(fixed_choices(vec![TestQuestion(1, 1000, "Easy")], DIFFICULTY_HARD).is_empty());
+    }
+
+    #[test]
+    fn test_mixed_choices_rotates_bands() {
+        assert_eq!(ids(&mixed_choices(bank(), 0)), vec![1]);
+        assert_eq!(ids(&mixed_choices(bank(), 1)), vec![2]);
+        assert_eq!(ids(&mixed_choices(bank(), 5)), vec![3, 4]);
+        let no_easy = vec![TestQuestion(2, 1200, "Medium"), TestQuestion(3, 1400, "Hard")];
+        assert_eq!(ids(&mixed_choices(no_easy, 3)), vec![2, 3], "Falls back to anything when the band is empty");
+    }
+}
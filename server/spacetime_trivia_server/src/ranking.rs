@@ -0,0 +1,26 @@
+/// Competition ranking ("1224") for values sorted best first: equal values share a rank, and
+/// the next value ranks as if they had not tied.
+pub fn competition_ranks<T: PartialEq>(sorted_values: &[T]) -> Vec<u32> {
+    let mut ranks = Vec::with_capacity(sorted_values.len());
+    for (i, value) in sorted_values.iter().enumerate() {
+        if i == 0 || sorted_values[i - 1] != *value {
+            ranks.push(i as u32 + 1);
+        } else {
+            ranks.push(ranks[i - 1]);
+        }
+    }
+    ranks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_competition_ranks_share_ties_and_skip_places() {
+        assert_eq!(competition_ranks(&[30, 20, 20, 10]), vec![1, 2, 2, 4]);
+        assert_eq!(competition_ranks(&[5, 5, 5]), vec![1, 1, 1]);
+        assert_eq!(competition_ranks(&[0]), vec![1]);
+        assert!(competition_ranks::<u32>(&[]).is_empty());
+    }
+}
@@ -0,0 +1,203 @@
+/// The lobbies a disconnected player loses their seat in once the grace period is over:
+/// none if they came back, otherwise every lobby still waiting for its game to start.
+/// In-game seats are kept so the player can resume.
+///
+/// `memberships` holds `(lobby_id, lobby_is_waiting)` for each lobby the player sits in.
+pub fn seats_to_vacate<L: Copy>(online: bool, memberships: &[(L, bool)]) -> Vec<L> {
+    if online {
+        return Vec::new();
+    }
+    memberships.iter().filter(|(_, waiting)| *waiting).map(|(lobby_id, _)| *lobby_id).collect()
+}
+
+/// A lobby member as host succession sees them.
+#[derive(Clone, Debug)]
+pub struct Seat<P> {
+    pub player_id: P,
+    pub is_player: bool, // Spectators never take over
+    pub online: bool,
+    pub joined_at_micros: i64,
+}
+
+/// What happens to a lobby's host role when a member leaves it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HostChange<P> {
+    Keep,
+    HandOff(P),
+    Abandon,
+}
+
+/// The member who takes over from `host`: the other player who has been in the lobby longest,
+/// preferring players who are online. `None` when no other player is left, even if spectators remain.
+pub fn host_successor<P: Copy + PartialEq>(seats: &[Seat<P>], host: P) -> Option<P> {
+    seats.iter()
+        .filter(|s| s.player_id != host && s.is_player)
+        .min_by_key(|s| (!s.online, s.joined_at_micros))
+        .map(|s| s.player_id)
+}
+
+/// The host change after `leaving` left an open lobby, given the seats that remain. The host's
+/// role passes on, or the lobby is abandoned when no player is left to take it. A waiting lobby
+/// that nobody is left in is abandoned whoever left.
+pub fn after_leaving<P: Copy + PartialEq>(seats_left: &[Seat<P>], host: P, leaving: P, lobby_waiting: bool) -> HostChange<P> {
+    if leaving == host {
+        return match host_successor(seats_left, host) {
+            Some(successor) => HostChange::HandOff(successor),
+            None => HostChange::Abandon,
+        };
+    }
+    if lobby_waiting && seats_left.is_empty() {
+        return HostChange::Abandon;
+    }
+    HostChange::Keep
+}
+
+/// The host change when `host` disconnects: the role passes straight away only to another
+/// player who is online. Otherwise the lobby waits out the host's grace period.
+pub fn on_host_disconnect<P: Copy + PartialEq>(seats: &[Seat<P>], host: P) -> HostChange<P> {
+    let online: Vec<Seat<P>> = seats.iter().filter(|s| s.online).cloned().collect();
+    match host_successor(&online, host) {
+        Some(successor) => HostChange::HandOff(successor),
+        None => HostChange::Keep,
+    }
+}
+
+/// The host change once a disconnected host's grace period is over. A host who came back keeps
+/// the role; otherwise it passes to any other player, online or not, and the lobby is abandoned
+/// when no player is left to take it.
+pub fn after_host_grace_period<P: Copy + PartialEq>(seats: &[Seat<P>], host: P, host_online: bool) -> HostChange<P> {
+    if host_online {
+        return HostChange::Keep;
+    }
+    match host_successor(seats, host) {
+        Some(successor) => HostChange::HandOff(successor),
+        None => HostChange::Abandon,
+    }
+}
+
+/// Where a reconnecting player picks up: the lobby and round their session points at, as long as
+/// `still_seated` confirms they hold a seat in that lobby and it is still open. `None` when there
+/// is nothing to resume; a session pointing at a lobby that is gone should then be cleared.
+pub fn resume_point<L: Copy, R: Copy>(lobby_id: Option<L>, round_id: Option<R>, still_seated: impl FnOnce(L) -> bool) -> Option<(L, Option<R>)> {
+    lobby_id.filter(|&lobby_id| still_seated(lobby_id)).map(|lobby_id| (lobby_id, round_id))
+}
+
+/// When a spectator who asked to play takes their seat.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LiveJoin {
+    Now, // The game has not started yet
+    NextRound, // Seated at the next round boundary, so nobody joins a round halfway
+}
+
+/// When a spectator can join the game as a player, or `None` if they cannot: a battle royale
+/// takes no new players once it has started.
+pub fn live_join(game_started: bool, battle_royale: bool) -> Option<LiveJoin> {
+    match (game_started, battle_royale) {
+        (false, _) => Some(LiveJoin::Now),
+        (true, false) => Some(LiveJoin::NextRound),
+        (true, true) => None,
+    }
+}
+
+/// The lobbies among `guest_lobbies` whose seat moves to the account when a guest merges into it;
+/// the guest's seat in a lobby the account also sat in is dropped instead.
+pub fn seats_to_take_over<L: Copy + PartialEq>(guest_lobbies: &[L], account_lobbies: &[L]) -> Vec<L> {
+    guest_lobbies.iter().filter(|lobby_id| !account_lobbies.contains(lobby_id)).copied().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seat(player_id: u8, is_player: bool, online: bool, joined_at_micros: i64) -> Seat<u8> {
+        Seat { player_id, is_player, online, joined_at_micros }
+    }
+
+    #[test]
+    fn test_host_successor_prefers_longest_present_online_player() {
+        let seats = [seat(1, true, true, 0), seat(3, true, true, 5), seat(2, true, true, 1)];
+        assert_eq!(host_successor(&seats, 1), Some(2), "Bot 2 joined before Bot 3");
+
+        let seats = [seat(1, true, true, 0), seat(2, true, false, 1), seat(3, true, true, 5)];
+        assert_eq!(host_successor(&seats, 1), Some(3), "Online players go before offline ones");
+
+        let seats = [seat(1, true, true, 0), seat(2, true, false, 1)];
+        assert_eq!(host_successor(&seats, 1), Some(2), "An offline player beats nobody");
+    }
+
+    #[test]
+    fn test_host_successor_skips_spectators() {
+        let seats = [seat(1, true, true, 0), seat(2, false, true, 1)];
+        assert_eq!(host_successor(&seats, 1), None);
+    }
+
+    #[test]
+    fn test_after_leaving_hands_off_then_abandons() {
+        let seats_left = [seat(2, true, true, 1)];
+        assert_eq!(after_leaving(&seats_left, 1, 1, true), HostChange::HandOff(2));
+        assert_eq!(after_leaving(&[], 2, 2, true), HostChange::Abandon, "The last member leaving abandons the lobby");
+        assert_eq!(after_leaving(&[seat(3, false, true, 2)], 2, 2, false), HostChange::Abandon, "Spectators alone cannot keep a lobby");
+    }
+
+    #[test]
+    fn test_after_leaving_non_host_keeps_host_unless_waiting_lobby_empties() {
+        let seats_left = [seat(1, true, true, 0)];
+        assert_eq!(after_leaving(&seats_left, 1, 2, true), HostChange::Keep);
+        assert_eq!(after_leaving(&[], 1, 2, true), HostChange::Abandon);
+        assert_eq!(after_leaving(&[], 1, 2, false), HostChange::Keep, "Only waiting lobbies are abandoned when emptied");
+    }
+
+    #[test]
+    fn test_on_host_disconnect_hands_off_only_to_online_players() {
+        let seats = [seat(1, true, false, 0), seat(2, true, false, 1), seat(3, true, true, 5)];
+        assert_eq!(on_host_disconnect(&seats, 1), HostChange::HandOff(3));
+        let seats = [seat(1, true, false, 0), seat(2, true, false, 1), seat(3, false, true, 2)];
+        assert_eq!(on_host_disconnect(&seats, 1), HostChange::Keep, "Offline players and spectators wait for the grace period");
+        assert_eq!(on_host_disconnect(&[seat(1, true, false, 0)], 1), HostChange::Keep, "A lone host is not abandoned on disconnect");
+    }
+
+    #[test]
+    fn test_after_host_grace_period_keeps_returning_host() {
+        let seats = [seat(1, true, true, 0), seat(2, true, false, 1)];
+        assert_eq!(after_host_grace_period(&seats, 1, true), HostChange::Keep);
+        assert_eq!(after_host_grace_period(&[seat(1, true, true, 0)], 1, true), HostChange::Keep);
+    }
+
+    #[test]
+    fn test_after_host_grace_period_hands_off_to_offline_player_or_abandons() {
+        let seats = [seat(1, true, false, 0), seat(2, true, false, 1)];
+        assert_eq!(after_host_grace_period(&seats, 1, false), HostChange::HandOff(2));
+        let seats = [seat(1, true, false, 0), seat(2, false, true, 1)];
+        assert_eq!(after_host_grace_period(&seats, 1, false), HostChange::Abandon);
+    }
+
+    #[test]
+    fn test_seats_to_vacate_only_waiting_lobbies_of_offline_players() {
+        let memberships = [(1, true), (2, false), (3, true)];
+        assert_eq!(seats_to_vacate(false, &memberships), vec![1, 3], "In-game seats survive the grace period");
+        assert!(seats_to_vacate(true, &memberships).is_empty(), "Reconnecting within the grace period keeps every seat");
+        assert!(seats_to_vacate::<u64>(false, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_resume_point_needs_a_seat_in_an_open_lobby() {
+        assert_eq!(resume_point(Some(7), Some(3), |_| true), Some((7, Some(3))), "Mid-game reconnects resume the round");
+        assert_eq!(resume_point(Some(7), None::<u64>, |_| true), Some((7, None)), "Waiting lobbies resume without a round");
+        assert_eq!(resume_point(Some(7), Some(3), |lobby_id| lobby_id != 7), None, "A lost seat is not resumed");
+        assert_eq!(resume_point(None::<u64>, Some(3), |_| panic!("no lobby to check")), None);
+    }
+
+    #[test]
+    fn test_live_join_waits_for_the_next_round_once_started() {
+        assert_eq!(live_join(false, false), Some(LiveJoin::Now));
+        assert_eq!(live_join(false, true), Some(LiveJoin::Now), "A royale can still be joined before it starts");
+        assert_eq!(live_join(true, false), Some(LiveJoin::NextRound), "Still spectating until the round ends");
+        assert_eq!(live_join(true, true), None);
+    }
+
+    #[test]
+    fn test_seats_to_take_over_skips_lobbies_the_account_sat_in() {
+        assert_eq!(seats_to_take_over(&[1, 2, 3], &[2]), vec![1, 3]);
+        assert!(seats_to_take_over::<u64>(&[], &[2]).is_empty());
+    }
+}
@@ -0,0 +1,107 @@
+// A streak of correct answers earns a combo every this many rounds (3, 6, 9...)
+pub const COMBO_STREAK: u32 = 3;
+
+/// A player's streak after a round they answered: one longer when correct, broken otherwise.
+/// Returns the new streak and whether it earns a combo.
+pub fn extend_streak(streak: u32, correct: bool) -> (u32, bool) {
+    if !correct {
+        return (0, false);
+    }
+    let streak = streak + 1;
+    (streak, streak.is_multiple_of(COMBO_STREAK))
+}
+
+/// A player's running totals on a lobby scoreboard.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ScoreLine {
+    pub points: u32,
+    pub correct_count: u32,
+    pub streak: u32,
+}
+
+/// Adds a round the player answered to their scoreboard line; any points mean a correct answer.
+/// Returns the new line and whether its streak earns a combo.
+pub fn add_round(line: ScoreLine, points: u32) -> (ScoreLine, bool) {
+    let correct = points > 0;
+    let (streak, combo) = extend_streak(line.streak, correct);
+    let line = ScoreLine {
+        points: line.points + points,
+        correct_count: line.correct_count + correct as u32,
+        streak,
+    };
+    (line, combo)
+}
+
+/// The correct answers among `(player_id, points, submitted_at_micros)`, fastest first, as
+/// `(player_id, points)`.
+pub fn correct_by_speed<P: Copy>(answers: &[(P, u32, i64)]) -> Vec<(P, u32)> {
+    let mut correct: Vec<&(P, u32, i64)> = answers.iter().filter(|(_, points, _)| *points > 0).collect();
+    correct.sort_by_key(|(_, _, submitted_at)| *submitted_at);
+    correct.into_iter().map(|&(player_id, points, _)| (player_id, points)).collect()
+}
+
+/// Per-choice answer counts padded so that all `choices` appear, even those nobody picked.
+pub fn padded_counts(mut counts: Vec<u32>, choices: usize) -> Vec<u32> {
+    counts.resize(counts.len().max(choices), 0);
+    counts
+}
+
+/// One scoreboard line for two lines in the same lobby, when a guest who played in it merges
+/// into an account that did too: points and correct answers add up and the longer streak stays.
+pub fn merge_lines(account: ScoreLine, guest: ScoreLine) -> ScoreLine {
+    ScoreLine {
+        points: account.points + guest.points,
+        correct_count: account.correct_count + guest.correct_count,
+        streak: account.streak.max(guest.streak),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extend_streak_awards_combo_at_every_multiple() {
+        assert_eq!(extend_streak(COMBO_STREAK - 1, true), (COMBO_STREAK, true));
+        assert_eq!(extend_streak(COMBO_STREAK, true), (COMBO_STREAK + 1, false));
+        assert_eq!(extend_streak(2 * COMBO_STREAK - 1, true), (2 * COMBO_STREAK, true));
+        assert_eq!(extend_streak(0, true), (1, COMBO_STREAK == 1));
+    }
+
+    #[test]
+    fn test_extend_streak_wrong_answer_breaks_streak() {
+        assert_eq!(extend_streak(COMBO_STREAK - 1, false), (0, false), "A wrong answer breaks the streak instead");
+    }
+
+    #[test]
+    fn test_add_round_accumulates_line() {
+        let (line, combo) = add_round(ScoreLine::default(), 10);
+        assert_eq!(line, ScoreLine { points: 10, correct_count: 1, streak: 1 });
+        assert!(!combo);
+        let (line, _) = add_round(line, 0);
+        assert_eq!(line, ScoreLine { points: 10, correct_count: 1, streak: 0 }, "A wrong answer keeps the points and breaks the streak");
+        let line = ScoreLine { points: 40, correct_count: 2, streak: COMBO_STREAK - 1 };
+        assert_eq!(add_round(line, 20), (ScoreLine { points: 60, correct_count: 3, streak: COMBO_STREAK }, true));
+    }
+
+    #[test]
+    fn test_correct_by_speed_orders_fastest_first() {
+        let answers = [(1, 0, 100), (2, 10, 300), (3, 10, 200)];
+        assert_eq!(correct_by_speed(&answers), vec![(3, 10), (2, 10)]);
+        assert!(correct_by_speed(&[(1, 0, 100)]).is_empty());
+    }
+
+    #[test]
+    fn test_padded_counts_lists_every_choice() {
+        assert_eq!(padded_counts(vec![2, 0, 1], 4), vec![2, 0, 1, 0]);
+        assert_eq!(padded_counts(vec![], 2), vec![0, 0]);
+        assert_eq!(padded_counts(vec![1, 1], 2), vec![1, 1]);
+    }
+
+    #[test]
+    fn test_merge_lines_adds_up_and_keeps_longer_streak() {
+        let account = ScoreLine { points: 30, correct_count: 3, streak: 1 };
+        let guest = ScoreLine { points: 20, correct_count: 2, streak: 2 };
+        assert_eq!(merge_lines(account, guest), ScoreLine { points: 50, correct_count: 5, streak: 2 });
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+
+use crate::elo::{calculate_team_elo_deltas, team_rating};
+
+/// Each topic's contest for the per-topic ratings: the points every settled player scored on it.
+///
+/// `answers` holds `(topic, player, points)` for every answer of the game. Only players
+/// `is_settled` accepts take part, and a topic needs two of them to be a contest.
+pub fn topic_contests<P: Copy + PartialEq>(answers: &[(String, P, u32)], is_settled: impl Fn(P) -> bool) -> BTreeMap<String, Vec<(P, u32)>> {
+    let mut contests: BTreeMap<String, Vec<(P, u32)>> = BTreeMap::new();
+    for (topic, player, points) in answers.iter().filter(|(_, player, _)| is_settled(*player)) {
+        let scores = contests.entry(topic.clone()).or_default();
+        match scores.iter_mut().find(|(p, _)| p == player) {
+            Some((_, total)) => *total += points,
+            None => scores.push((*player, *points)),
+        }
+    }
+    contests.retain(|_, scores| scores.len() >= 2);
+    contests
+}
+
+/// Who a game settles, with the points they finished on in the lobby, best first.
+///
+/// `scoreboard` holds the lobby's `(player, points)`; lifetime points never enter the ranking.
+/// A normal game settles everyone on it except spectators. A battle royale settles exactly the
+/// players it started with, `royale_players`, eliminated or not, even those who never scored.
+pub fn settled_players<P: Copy + PartialEq>(scoreboard: &[(P, u32)], spectators: &[P], royale_players: Option<&[P]>) -> Vec<(P, u32)> {
+    let points_of = |player: P| scoreboard.iter().find(|(p, _)| *p == player).map_or(0, |(_, points)| *points);
+    let mut settled: Vec<(P, u32)> = match royale_players {
+        Some(players) => players.iter().map(|&player| (player, points_of(player))).collect(),
+        None => scoreboard.iter().filter(|(player, _)| !spectators.contains(player)).copied().collect(),
+    };
+    settled.sort_by_key(|(_, points)| std::cmp::Reverse(*points));
+    settled
+}
+
+/// Elo deltas for a team game from each player's `(elo, k_factor)` and `team_ids`, parallel to
+/// it. Teams are rated pairwise on `team_points`, each at the mean Elo and mean K-factor of its
+/// members, and each team's delta is shared among its members so that uneven teams still
+/// settle zero-sum. Players without a team are not rated.
+pub fn team_member_deltas<T: Copy + Ord>(members: &[(i32, f32)], team_ids: &[Option<T>], team_points: impl Fn(T) -> u32) -> Vec<i32> {
+    let mut rated_teams: Vec<T> = team_ids.iter().flatten().copied().collect();
+    rated_teams.sort();
+    rated_teams.dedup();
+
+    let mut teams: Vec<(usize, i32, u32)> = Vec::new();
+    let mut k_factors: Vec<f32> = Vec::new();
+    for &team_id in &rated_teams {
+        let team: Vec<(i32, f32)> = members.iter().zip(team_ids)
+            .filter(|(_, t)| **t == Some(team_id))
+            .map(|(member, _)| *member)
+            .collect();
+        teams.push((team.len(), team_rating(&team.iter().map(|(elo, _)| *elo).collect::<Vec<_>>()), team_points(team_id)));
+        k_factors.push(team.iter().map(|(_, k)| k).sum::<f32>() / team.len() as f32);
+    }
+    let member_deltas = calculate_team_elo_deltas(&teams, &k_factors);
+
+    // Members take their team's deltas in the order they appear in `members`
+    let mut next_member = vec![0; rated_teams.len()];
+    team_ids.iter()
+        .map(|team_id| match team_id.and_then(|id| rated_teams.iter().position(|t| *t == id)) {
+            Some(i) => {
+                next_member[i] += 1;
+                member_deltas[i][next_member[i] - 1]
+            }
+            None => 0,
+        })
+        .collect()
+}
+
+/// A player's result in a closed season.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SeasonLine {
+    pub final_elo: i32,
+    pub games_played: u32,
+    pub score: u32,
+}
+
+/// One season result for a guest and the account they merge into, when both played the season.
+/// Like the merged player, it keeps the Elo of whichever played more games; the account's on a
+/// tie. Games and points add up.
+pub fn merge_season_lines(account: SeasonLine, guest: SeasonLine) -> SeasonLine {
+    SeasonLine {
+        final_elo: if guest.games_played > account.games_played { guest.final_elo } else { account.final_elo },
+        games_played: account.games_played + guest.games_played,
+        score: account.score + guest.score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(topic: &str, player: u8, points: u32) -> (String, u8, u32) {
+        (topic.to_string(), player, points)
+    }
+
+    #[test]
+    fn test_topic_contests_add_up_points_per_topic() {
+        let answers = [answer("Science", 1, 10), answer("Science", 2, 0), answer("History", 1, 0), answer("History", 2, 20), answer("Science", 1, 20)];
+        let contests = topic_contests(&answers, |_| true);
+        assert_eq!(contests["Science"], vec![(1, 30), (2, 0)]);
+        assert_eq!(contests["History"], vec![(1, 0), (2, 20)]);
+    }
+
+    #[test]
+    fn test_topic_contests_need_two_settled_players() {
+        // Player 3 answered but is not settled, which leaves Science with a single contestant
+        let answers = [answer("Science", 1, 10), answer("Science", 3, 0), answer("Art", 1, 10), answer("Art", 2, 10)];
+        let contests = topic_contests(&answers, |player| player != 3);
+        assert!(!contests.contains_key("Science"));
+        assert_eq!(contests["Art"], vec![(1, 10), (2, 10)]);
+    }
+
+    #[test]
+    fn test_settled_players_rank_on_lobby_points_without_spectators() {
+        let scoreboard = [(1, 10), (2, 20), (3, 5), (4, 30)];
+        assert_eq!(settled_players(&scoreboard, &[4], None), vec![(2, 20), (1, 10), (3, 5)]);
+        assert!(settled_players::<u8>(&[], &[], None).is_empty());
+    }
+
+    #[test]
+    fn test_settled_players_battle_royale_settles_its_starting_players() {
+        // Player 4 was eliminated before scoring; player 5 only watched
+        let scoreboard = [(1, 10), (2, 20), (5, 40)];
+        assert_eq!(settled_players(&scoreboard, &[4, 5], Some(&[1, 2, 4])), vec![(2, 20), (1, 10), (4, 0)]);
+    }
+
+    #[test]
+    fn test_team_member_deltas_move_members_by_team_result() {
+        // Equal team ratings, provisional K = 40: the winning team's members each gain 20
+        let members = [(1500, 40.0), (1400, 40.0), (1300, 40.0), (1200, 40.0)];
+        let team_ids = [Some(1), Some(2), Some(2), Some(1)];
+        let deltas = team_member_deltas(&members, &team_ids, |team| if team == 1 { 10 } else { 0 });
+        assert_eq!(deltas, vec![20, -20, -20, 20]);
+    }
+
+    #[test]
+    fn test_team_member_deltas_skip_players_without_a_team() {
+        let members = [(1200, 40.0), (1200, 40.0), (1200, 40.0)];
+        let deltas = team_member_deltas(&members, &[Some(1), None, Some(2)], |team| team * 10);
+        assert_eq!(deltas[1], 0);
+        assert_eq!(deltas[0] + deltas[2], 0, "Team settlement is zero-sum");
+        assert!(deltas[2] > 0);
+    }
+
+    #[test]
+    fn test_merge_season_lines_keeps_more_experienced_elo() {
+        let account = SeasonLine { final_elo: 1300, games_played: 5, score: 100 };
+        let guest = SeasonLine { final_elo: 1250, games_played: 12, score: 40 };
+        assert_eq!(merge_season_lines(account, guest), SeasonLine { final_elo: 1250, games_played: 17, score: 140 });
+        assert_eq!(merge_season_lines(guest, guest).final_elo, 1250, "A tie keeps the account's Elo");
+    }
+}
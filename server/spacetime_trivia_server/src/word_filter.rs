@@ -0,0 +1,184 @@
+use std::ops::Range;
+
+/// Words that may not appear in player-chosen text. Matched per word after normalization, so
+/// "Sh1t" and "s.h.i.t" are caught while "Scunthorpe" and "Dickens" are not.
+const BLOCKED_WORDS: &[&str] = &[
+    "fuck", "shit", "cunt", "bitch", "asshole", "bastard", "dick", "pussy", "slut", "whore", "nigger", "faggot",
+];
+
+/// Endings that still make a word count as its blocked stem ("shitty", "bitches", "dickhead").
+const BLOCKED_SUFFIXES: &[&str] = &["s", "es", "y", "ty", "ter", "ters", "er", "ers", "ed", "ing", "head", "heads", "face"];
+
+/// A word as the filter sees it.
+struct Word {
+    letters: String, // Normalized
+    spans: Vec<Range<usize>>, // Byte ranges of the text it covers, one per character when spelled out
+    spelled_out: bool, // Written one character at a time with separators in between
+}
+
+/// Symbols that stand in for letters inside a word, but are punctuation at its end.
+fn is_substitute_symbol(c: char) -> bool {
+    matches!(c, '!' | '|' | '@' | '$')
+}
+
+/// Lowercases a word, undoes common character substitutions and drops anything that is
+/// not a letter.
+fn normalize(word: &str) -> String {
+    word.chars()
+        .map(|c| match c {
+            '0' => 'o',
+            '1' | '!' | '|' => 'i',
+            '3' => 'e',
+            '4' | '@' => 'a',
+            '5' | '$' => 's',
+            '7' => 't',
+            _ => c,
+        })
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Byte ranges of the runs of letters, digits and substitute symbols in `text`, with trailing
+/// substitute symbols (and leading '!' or '|') left to the punctuation around them.
+fn tokens(text: &str) -> Vec<Range<usize>> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+        let is_word_char = c.is_alphanumeric() || is_substitute_symbol(c);
+        match (start, is_word_char) {
+            (None, true) => start = Some(i),
+            (Some(s), false) => {
+                let token = &text[s..i];
+                let trimmed_end = token.trim_end_matches(is_substitute_symbol);
+                let trimmed = trimmed_end.trim_start_matches(['!', '|']);
+                if !trimmed.is_empty() {
+                    let offset = s + trimmed_end.len() - trimmed.len();
+                    tokens.push(offset..offset + trimmed.len());
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+/// Splits a token where its case changes, so "BigSHITenergy" reads as "Big", "SHIT", "energy".
+fn split_camel_case(text: &str, token: Range<usize>) -> Vec<Range<usize>> {
+    let chars: Vec<(usize, char)> = text[token.clone()].char_indices().map(|(i, c)| (token.start + i, c)).collect();
+    let mut parts = Vec::new();
+    let mut start = token.start;
+    for k in 1..chars.len() {
+        let (at, c) = chars[k];
+        let prev = chars[k - 1].1;
+        let lower_to_upper = prev.is_lowercase() && c.is_uppercase();
+        let acronym_end = k >= 2 && c.is_lowercase() && prev.is_uppercase() && chars[k - 2].1.is_uppercase();
+        if lower_to_upper || acronym_end {
+            parts.push(start..at);
+            start = at;
+        }
+    }
+    parts.push(start..token.end);
+    parts
+}
+
+/// Breaks `text` into the words the filter checks. Separators only join single characters:
+/// "s h i t" and "s.h.i.t" are read as one spelled-out word, "Push It" stays two words.
+fn words(text: &str) -> Vec<Word> {
+    let tokens = tokens(text);
+    let mut words = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        let run = tokens[i..].iter().take_while(|t| text[(*t).clone()].chars().count() == 1).count();
+        if run >= 2 {
+            let spans = tokens[i..i + run].to_vec();
+            let letters = spans.iter().map(|span| normalize(&text[span.clone()])).collect();
+            words.push(Word { letters, spans, spelled_out: true });
+            i += run;
+        } else {
+            for part in split_camel_case(text, tokens[i].clone()) {
+                words.push(Word { letters: normalize(&text[part.clone()]), spans: vec![part], spelled_out: false });
+            }
+            i += 1;
+        }
+    }
+    words
+}
+
+/// A word is blocked when it is a blocked word, possibly with a common ending. A spelled-out
+/// word is blocked when it contains one anywhere.
+fn is_blocked(word: &Word) -> bool {
+    BLOCKED_WORDS.iter().any(|blocked| {
+        if word.spelled_out {
+            return word.letters.contains(blocked);
+        }
+        word.letters.strip_prefix(blocked).is_some_and(|rest| rest.is_empty() || BLOCKED_SUFFIXES.contains(&rest))
+    })
+}
+
+/// Returns true if any word of the text is a blocked word, after undoing substitutions,
+/// case tricks and letters spelled out with separators.
+pub fn contains_blocked_word(text: &str) -> bool {
+    words(text).iter().any(is_blocked)
+}
+
+/// Replaces every character of each blocked word with an asterisk, keeping the rest of the
+/// message, its separators and spacing intact. Words are found as in `contains_blocked_word`.
+pub fn mask_blocked_words(text: &str) -> String {
+    let masked: Vec<Range<usize>> = words(text).into_iter().filter(is_blocked).flat_map(|w| w.spans).collect();
+    text.char_indices()
+        .map(|(i, c)| if masked.iter().any(|span| span.contains(&i)) { '*' } else { c })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clean_text_passes() {
+        assert!(!contains_blocked_word("Quiz_Wizard"));
+        assert!(!contains_blocked_word("Player_1a2b3c4d"));
+        assert!(!contains_blocked_word(""));
+    }
+
+    #[test]
+    fn test_blocked_word_is_caught_in_any_case() {
+        assert!(contains_blocked_word("shit"));
+        assert!(contains_blocked_word("BigSHITenergy"));
+    }
+
+    #[test]
+    fn test_separators_and_substitutions_do_not_hide_blocked_word() {
+        assert!(contains_blocked_word("s.h.i.t"));
+        assert!(contains_blocked_word("sh1t_happens"));
+        assert!(contains_blocked_word("B!tch"));
+    }
+
+    #[test]
+    fn test_words_containing_a_blocked_word_pass() {
+        assert!(!contains_blocked_word("Scunthorpe"));
+        assert!(!contains_blocked_word("Dickens"));
+        assert!(!contains_blocked_word("Push It"));
+        assert!(!contains_blocked_word("Bass Hole"));
+        assert!(!contains_blocked_word("Wow!"));
+    }
+
+    #[test]
+    fn test_spelled_out_and_suffixed_blocked_words_are_caught() {
+        assert!(contains_blocked_word("s h i t"));
+        assert!(contains_blocked_word("you f-u-c-k"));
+        assert!(contains_blocked_word("sh1tty"));
+        assert!(contains_blocked_word("shit!!"));
+    }
+
+    #[test]
+    fn test_mask_blocked_words_only_masks_offending_words() {
+        assert_eq!(mask_blocked_words("what the sh1t  is this"), "what the ****  is this");
+        assert_eq!(mask_blocked_words("all clean here"), "all clean here");
+        assert_eq!(mask_blocked_words("you s h i t!"), "you * * * *!", "Spelled-out words are masked as they are blocked in names");
+        assert_eq!(mask_blocked_words("BigSHITenergy from Scunthorpe"), "Big****energy from Scunthorpe");
+        assert_eq!(mask_blocked_words(""), "");
+    }
+}